@@ -1,641 +1,5324 @@
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::ops::Deref;
+use std::process;
+use std::sync::OnceLock;
+
+// 类型枚举
+#[derive(Clone, Debug, PartialEq)]
+enum VarType {
+    Int, Double, Str, Bool,
+    List(Box<VarType>),
+    Map(Box<VarType>, Box<VarType>),
+    Set(Box<VarType>),
+    Deque(Box<VarType>),
+    // 元组 (int, str)
+    Tuple(Vec<VarType>),
+    // 可选值 opt int，可以为 none
+    Opt(Box<VarType>),
+    // 用户定义的结构体类型，按名称引用
+    Named(String),
+    Unknown,
+}
+
+// 解析类型名，支持嵌套列表类型如 list int / list list str，以及 map str int、set int、deque int、(int, str)
+fn parse_type(s: &str) -> VarType {
+    let s = s.trim();
+    if let Some(inner) = s.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        return VarType::Tuple(split_top_level(inner, ',').iter().map(|t| parse_type(t)).collect());
+    }
+    if let Some(inner) = s.strip_prefix("list ") {
+        return VarType::List(Box::new(parse_type(inner)));
+    }
+    if let Some(inner) = s.strip_prefix("set ") {
+        return VarType::Set(Box::new(parse_type(inner)));
+    }
+    if let Some(inner) = s.strip_prefix("deque ") {
+        return VarType::Deque(Box::new(parse_type(inner)));
+    }
+    if let Some(inner) = s.strip_prefix("opt ") {
+        return VarType::Opt(Box::new(parse_type(inner)));
+    }
+    if let Some(inner) = s.strip_prefix("map ") {
+        // 键只能是简单类型，值可以是任意类型
+        let (key, value) = inner.trim().split_once(char::is_whitespace).unwrap_or((inner, ""));
+        return VarType::Map(Box::new(parse_type(key)), Box::new(parse_type(value)));
+    }
+    match s {
+        "int" => VarType::Int,
+        "double" => VarType::Double,
+        "str" => VarType::Str,
+        "bool" => VarType::Bool,
+        "list" => VarType::List(Box::new(VarType::Unknown)),
+        "map" => VarType::Map(Box::new(VarType::Unknown), Box::new(VarType::Unknown)),
+        "set" => VarType::Set(Box::new(VarType::Unknown)),
+        "deque" => VarType::Deque(Box::new(VarType::Unknown)),
+        _ if is_identifier(s) && !s.starts_with(|c: char| c.is_ascii_digit()) => VarType::Named(s.to_string()),
+        _ => VarType::Unknown,
+    }
+}
+
+// 类型中引用的结构体是否都已定义
+fn type_defined(vtype: &VarType, prog: &Program) -> bool {
+    match vtype {
+        VarType::List(inner) | VarType::Set(inner) | VarType::Deque(inner) | VarType::Opt(inner) => type_defined(inner, prog),
+        VarType::Map(key, value) => type_defined(key, prog) && type_defined(value, prog),
+        VarType::Tuple(types) => types.iter().all(|t| *t != VarType::Unknown && type_defined(t, prog)),
+        VarType::Named(name) => prog.structs.contains_key(name) || prog.enums.contains_key(name),
+        _ => true,
+    }
+}
+
+// 参数/字段列表：[(类型, 名称)]
+type Fields = Vec<(String, String)>;
+
+// 有序键值对：字典条目或结构体的字段值
+type Entries = Vec<(String, String)>;
+
+// switch 分支：[(case 标签, case 所在行, 分支体)]
+type Cases = Vec<(String, SourceLine, Vec<SourceLine>)>;
+
+// 函数签名：(参数列表, 返回类型)
+type FunctionSig = (Fields, Option<String>);
+
+// 运行时错误的类别，catch 中用 err.kind 取得名称
+#[derive(Clone, Debug, PartialEq)]
+enum ErrorKind {
+    Syntax, Type, Name, Value, Index, Key, ZeroDivision, Io,
+    // throw 抛出的错误，类别由脚本给出（throw "..." 时为 Error）
+    Thrown(String),
+}
+
+impl ErrorKind {
+    fn name(&self) -> &str {
+        match self {
+            ErrorKind::Syntax => "SyntaxError",
+            ErrorKind::Type => "TypeError",
+            ErrorKind::Name => "NameError",
+            ErrorKind::Value => "ValueError",
+            ErrorKind::Index => "IndexError",
+            ErrorKind::Key => "KeyError",
+            ErrorKind::ZeroDivision => "ZeroDivisionError",
+            ErrorKind::Io => "IOError",
+            ErrorKind::Thrown(kind) => kind,
+        }
+    }
+
+    // 错误码：每个类别占一段编号
+    fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "E0001",
+            ErrorKind::Type => "E0100",
+            ErrorKind::Name => "E0200",
+            ErrorKind::Value => "E0300",
+            ErrorKind::Index => "E0400",
+            ErrorKind::Key => "E0500",
+            ErrorKind::ZeroDivision => "E0600",
+            ErrorKind::Io => "E0700",
+            ErrorKind::Thrown(_) => "E0800",
+        }
+    }
+
+    // 错误码所在编号段对应的类别，如 E0102 属于 E01xx 段，是类型错误
+    fn from_code(code: &str) -> ErrorKind {
+        match code.get(..3) {
+            Some("E00") => ErrorKind::Syntax,
+            Some("E01") => ErrorKind::Type,
+            Some("E02") => ErrorKind::Name,
+            Some("E03") => ErrorKind::Value,
+            Some("E04") => ErrorKind::Index,
+            Some("E05") => ErrorKind::Key,
+            Some("E06") => ErrorKind::ZeroDivision,
+            Some("E07") => ErrorKind::Io,
+            _ => ErrorKind::Thrown("Error".to_string()),
+        }
+    }
+
+    // 未捕获的错误使 hitcode 以对应的退出码结束；throw 抛出的错误为 1
+    fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Thrown(_) => 1,
+            ErrorKind::Syntax => 2,
+            ErrorKind::Type => 3,
+            ErrorKind::Name => 4,
+            ErrorKind::Value => 5,
+            ErrorKind::Index => 6,
+            ErrorKind::Key => 7,
+            ErrorKind::ZeroDivision => 8,
+            ErrorKind::Io => 9,
+        }
+    }
+
+    // 由名称还原类别，重新抛出捕获到的内置错误时保持原类别
+    fn from_name(name: &str) -> ErrorKind {
+        match name {
+            "SyntaxError" => ErrorKind::Syntax,
+            "TypeError" => ErrorKind::Type,
+            "NameError" => ErrorKind::Name,
+            "ValueError" => ErrorKind::Value,
+            "IndexError" => ErrorKind::Index,
+            "KeyError" => ErrorKind::Key,
+            "ZeroDivisionError" => ErrorKind::ZeroDivision,
+            "IOError" => ErrorKind::Io,
+            _ => ErrorKind::Thrown(name.to_string()),
+        }
+    }
+}
+
+// 诊断和提示信息使用的语言，用 --lang 或环境变量 LANG 选择，默认中文
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Lang {
+    #[default]
+    ZhCn,
+    EnUs,
+}
+
+impl Lang {
+    // 接受 zh-CN、en-US 以及 LANG 中常见的 zh_CN.UTF-8、en_US.UTF-8 等写法
+    fn from_name(name: &str) -> Option<Lang> {
+        let name = name.trim().to_ascii_lowercase();
+        let language = name.split(['-', '_', '.', '@']).next().unwrap_or_default();
+        match language {
+            "zh" => Some(Lang::ZhCn),
+            "en" => Some(Lang::EnUs),
+            _ => None,
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+// 当前语言：命令行没有指定时按 LANG 环境变量，识别不了时用中文
+fn lang() -> Lang {
+    *LANG.get_or_init(|| env::var("LANG").ok().and_then(|v| Lang::from_name(&v)).unwrap_or_default())
+}
+
+// 消息目录：(键, 中文, 英文)。诊断信息以错误码为键，其余提示和消息片段以名称为键
+// 模板中的 {} 依次取参数，{0} {1} 按位置取参数
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // 语法错误
+    ("E0002", "变量声明语法错误: {}", "Invalid variable declaration: {}"),
+    ("E0003", "常量声明语法错误: {}", "Invalid constant declaration: {}"),
+    ("E0004", "{}声明语法错误: {}", "Invalid {} declaration: {}"),
+    ("E0006", "for 语法错误: {}", "Invalid for loop: {}"),
+    ("E0007", "try 块后面需要 catch 或 finally", "A try block must be followed by catch or finally"),
+    ("E0008", "{} 必须紧跟在 try 块之后", "{} must directly follow a try block"),
+    ("E0009", "函数名后面要加括号才会调用: {}()", "A function is only called with parentheses: {}()"),
+    ("E0010", "缺少表达式", "Missing expression"),
+    ("E0011", "常量 {} 的初始值必须是编译期常量: {}", "The initial value of constant {} must be a compile-time constant: {}"),
+    ("E0012", "函数 {} 没有以 end(...) 结束", "Function {} is not closed with end(...)"),
+    ("E0013", "start: 块没有以 end() 结束", "The start: block is not closed with end()"),
+    ("E0014", "重复的 start: 块（第 {} 行已有 start:）", "Duplicate start: block (line {} already has start:)"),
+    ("E0015", "顶层语句不在任何 function 或 start: 块中: {}", "Top-level statement outside any function or start: block: {}"),
+    ("E0016", "程序缺少 start: 块", "The program has no start: block"),
+    ("E0017", "缩进混用了 Tab 和空格（可以用 --tab-width 指定 Tab 的宽度）", "Indentation mixes tabs and spaces (use --tab-width to set the tab width)"),
+    ("E0018", "这一行用 Tab 缩进，但第 {} 行用的是空格（可以用 --tab-width 指定 Tab 的宽度）", "This line is indented with tabs, but line {} uses spaces (use --tab-width to set the tab width)"),
+    ("E0019", "这一行用空格缩进，但第 {} 行用的是 Tab（可以用 --tab-width 指定 Tab 的宽度）", "This line is indented with spaces, but line {} uses tabs (use --tab-width to set the tab width)"),
+    ("E0020", "意外的缩进：上一行没有以 : 开启代码块", "Unexpected indent: the previous line does not open a block with :"),
+    ("E0021", "缺少缩进的代码块：上一行以 : 结尾，这一行应当比它缩进更深", "Expected an indented block: the previous line ends with :, so this line must be indented further"),
+    ("E0022", "缩进不一致：这一行的缩进与外层任何代码块都不对齐", "Inconsistent indentation: this line does not line up with any enclosing block"),
+    ("E0023", "未知的关键字集合: {}（可以是 zh 或 en）", "Unknown keyword set: {} (expected zh or en)"),
+    ("E0024", "字符串没有结束: {}（缺少结尾的双引号）", "Unterminated string: {} (missing closing double quote)"),
+    ("E0025", "三引号字符串没有结束：直到文件末尾都没有找到结尾的 \"\"\"", "Unterminated triple-quoted string: no closing \"\"\" before the end of the file"),
+    ("E0026", "未知的转义序列: {}（可以使用 \\n \\t \\\" \\\\ \\u{...}，或者改用原始字符串 r\"...\"）", "Unknown escape sequence: {} (use \\n \\t \\\" \\\\ \\u{...}, or a raw string r\"...\")"),
+    ("E0027", "无效的 Unicode 转义: {}（应写作 \\u{十六进制码点}，如 \\u{4E2D}）", "Invalid Unicode escape: {} (expected \\u{hex code point}, e.g. \\u{4E2D})"),
+    ("E0028", "块注释没有结束：直到文件末尾都没有找到结尾的 */", "Unterminated block comment: no closing */ before the end of the file"),
+    ("E0029", "无法识别的语句: {}", "Unrecognized statement: {}"),
+    // 类型错误
+    ("E0101", "{} 类型不匹配: {}", "Type mismatch for {}: {}"),
+    ("E0102", "{} 不是 opt 类型，不能赋值为 none", "{} is not an opt type and cannot be none"),
+    ("E0103", "{} 元素类型不匹配: {}", "Element type mismatch for {}: {}"),
+    ("E0104", "无法推断变量 {0} 的类型: {1}，请写明类型（如 var opt int {0} = none）", "Cannot infer the type of variable {0} from {1}; write the type explicitly (e.g. var opt int {0} = none)"),
+    ("E0105", "变量解构失败: {} 不是 {} 个元素的元组", "Destructuring failed: {} is not a tuple of {} elements"),
+    ("E0106", "函数 {} 的返回值与 ->return::{} 不匹配: {}", "The return value of function {} does not match ->return::{}: {}"),
+    ("E0107", "函数 {} 需要 {} 个参数，但给了 {} 个", "Function {} takes {} arguments but {} were given"),
+    ("E0108", "{} 只有 {} 个字段，但给了 {} 个值", "{} has only {} fields but {} values were given"),
+    ("E0109", "{} 不带载荷", "{} has no payload"),
+    ("E0110", "{} 不是列表或字典，不能下标访问", "{} is not a list or map and cannot be indexed"),
+    ("E0111", "{} 不是列表或字典，不能下标赋值", "{} is not a list or map and cannot be assigned by index"),
+    ("E0112", "{} 的类型是 {}，不能下标访问", "{} has type {} and cannot be indexed"),
+    ("E0113", "字典键类型不匹配: {}", "Map key type mismatch: {}"),
+    ("E0114", "{} 不是集合类型，不能求长度", "{} is not a collection and has no length"),
+    ("E0115", "{} 不支持方法 has", "{} does not support has"),
+    ("E0116", "{} 需要集合或列表参数: {}", "{} needs a set or list argument: {}"),
+    ("E0117", "{} 会修改容器，只能对已声明的集合变量调用", "{} modifies its container and can only be called on a declared collection variable"),
+    ("E0118", "{} 不支持方法 {}，或参数个数不对", "{} does not support method {}, or the number of arguments is wrong"),
+    ("E0119", "{} 的类型是 {}，不支持方法 {}，或参数个数不对", "{} has type {} and does not support method {}, or the number of arguments is wrong"),
+    ("E0120", "无法计算: {}", "Cannot evaluate: {}"),
+    ("E0121", "{} 不是数字（类型是 {}），不能参与运算", "{} is not a number (its type is {}) and cannot be used in arithmetic"),
+    ("E0122", "变量 {} 只能与数字运算: {}", "Variable {} can only be combined with numbers: {}"),
+    ("E0123", "{0} 的值是 none，不能在这里使用（请先用 if {0} is not none: 检查）", "{0} is none and cannot be used here (check it first with if {0} is not none:)"),
+    ("E0124", "变量 {} 的值是 none，不能做 {} 运算", "Variable {} is none and cannot be used with {}"),
+    ("E0125", "{0} 可能是 none，不能访问字段（请先用 if {0} is not none: 检查）", "{0} may be none, so its fields cannot be accessed (check it first with if {0} is not none:)"),
+    ("E0126", "{0} 可能是 none，不能下标访问（请先用 if {0} is not none: 检查）", "{0} may be none, so it cannot be indexed (check it first with if {0} is not none:)"),
+    ("E0127", "{0} 可能是 none，不能调用方法（请先用 if {0} is not none: 检查）", "{0} may be none, so its methods cannot be called (check it first with if {0} is not none:)"),
+    ("E0128", "条件求值失败: none 不能用 {} 比较: {}", "Condition failed: none cannot be compared with {}: {}"),
+    ("E0129", "{} 不是结构体，不能访问字段 {}", "{} is not a struct, so field {} cannot be accessed"),
+    ("E0130", "{} 是常量，不能修改", "{} is a constant and cannot be modified"),
+    ("E0131", "{} 需要列表: {}", "{} needs a list: {}"),
+    ("E0132", "{} 需要字典: {}", "{} needs a map: {}"),
+    ("E0133", "{} 需要集合: {}", "{} needs a set: {}"),
+    ("E0134", "throw 需要字符串或 Error 值: {}", "throw needs a string or an Error value: {}"),
+    ("E0135", "exit 需要整数退出码: {}", "exit needs an integer exit code: {}"),
+    ("E0136", "for 语法错误: {} 不是可遍历的集合", "Invalid for loop: {} cannot be iterated"),
+    ("E0137", "case 范围的边界必须是数字: {}", "case range bounds must be numbers: {}"),
+    // 名称错误
+    ("E0201", "{} 未定义", "{} is not defined"),
+    ("E0202", "变量 {0} 未定义，不能直接赋值（请用 var {0} = ...）", "Variable {0} is not defined and cannot be assigned (declare it with var {0} = ...)"),
+    ("E0203", "函数 {} 未定义", "Function {} is not defined"),
+    ("E0204", "列表 {} 未定义", "List {} is not defined"),
+    ("E0205", "say 语法错误: {} 不是已定义变量或字符串", "Invalid say: {} is neither a defined variable nor a string"),
+    ("E0206", "结构体 {} 没有字段 {}", "Struct {} has no field {}"),
+    ("E0207", "构造 {} 缺少字段 {}", "Missing field {1} when constructing {0}"),
+    ("E0208", "枚举 {} 没有成员 {}", "Enum {} has no variant {}"),
+    ("E0209", "{} 是函数参数，不能重复声明", "{} is a function parameter and cannot be redeclared"),
+    ("E0210", "{} 已在第 {} 行声明，不能重复声明", "{} is already declared on line {} and cannot be redeclared"),
+    // 值错误
+    ("E0301", "列表下标必须是非负整数: {}", "List index must be a non-negative integer: {}"),
+    ("E0302", "无法把输入 {} 转换为 {}", "Cannot convert input {} to {}"),
+    ("E0303", "无法把 {} 转换为 {}", "Cannot convert {} to {}"),
+    // 下标错误
+    ("E0401", "列表下标越界: {} (长度 {})", "List index out of range: {} (length {})"),
+    ("E0402", "元组下标越界: {} (长度 {})", "Tuple index out of range: {} (length {})"),
+    ("E0403", "双端队列 {} 为空", "Deque {} is empty"),
+    // 键错误
+    ("E0501", "字典中不存在键 {}", "Key {} not found in map"),
+    ("E0502", "字典 {} 中不存在键 {}", "Key {1} not found in map {0}"),
+    ("E0503", "集合 {} 中不存在元素 {}", "Element {1} not found in set {0}"),
+    // 除零错误
+    ("E0601", "除数为 0: {}", "Division by zero: {}"),
+    // 输入输出错误
+    ("E0701", "无法打开文件 {}: {}", "Cannot open file {}: {}"),
+    ("E0702", "读取文件 {} 失败: {}", "Failed to read file {}: {}"),
+    ("E0703", "写入文件 {} 失败: {}", "Failed to write file {}: {}"),
+    ("E0704", "输出失败: {}", "Failed to write output: {}"),
+    ("E0705", "读取输入失败: {}", "Failed to read input: {}"),
+    // 警告
+    ("W0001", "{} 中的 case 标签 {} 重复，后面的分支永远不会执行", "Duplicate case label {1} in {0}; the later branch can never run"),
+    ("W0002", "{} 中的 case {} 不是枚举 {} 的成员", "case {1} in {0} is not a variant of enum {2}"),
+    ("W0003", "{} 中的 case {} 绑定了 {} 个变量，但成员 {} 只有 {} 个载荷字段", "case {1} in {0} binds {2} variables, but variant {3} has only {4} payload fields"),
+    ("W0004", "{} 没有 default 分支，缺少枚举 {} 的成员: {}", "{} has no default branch and misses variants of enum {}: {}"),
+    ("W0005", "{0} '{1}'，是否应为 '{2}'？已按 '{2}' 处理（可以用 --fix 自动修正）", "{0} '{1}': did you mean '{2}'? Treated as '{2}' (use --fix to rewrite it)"),
+    // 消息中出错的对象
+    ("what.var", "变量 {}", "variable {}"),
+    ("what.const", "常量 {}", "constant {}"),
+    ("what.param", "函数 {} 的参数 {}", "parameter {1} of function {0}"),
+    ("what.field", "{} 的字段 {}", "field {1} of {0}"),
+    ("what.destructure", "变量解构", "destructuring"),
+    ("what.index", "列表下标", "list index"),
+    ("what.typed", "{} 的类型是 {}", "{} has type {}"),
+    ("what.eof", "输入已结束", "end of input"),
+    ("label.list", "列表", "list"),
+    ("label.map", "字典", "map"),
+    ("label.set", "集合", "set"),
+    ("label.deque", "双端队列", "deque"),
+    // 报告时加在消息前的语句上下文
+    ("ctx.destructure", "变量解构失败:", "Destructuring failed:"),
+    ("ctx.var_assign", "变量 {} 赋值失败:", "Assignment to variable {} failed:"),
+    ("ctx.const_assign", "常量 {} 赋值失败:", "Assignment to constant {} failed:"),
+    ("ctx.collection_assign", "{} {} 赋值失败:", "Assignment to {} {} failed:"),
+    ("ctx.say", "say 执行失败:", "say failed:"),
+    ("ctx.for", "for 执行失败:", "for failed:"),
+    ("ctx.switch", "switch 执行失败:", "switch failed:"),
+    ("ctx.case", "case {} 求值失败:", "case {} failed:"),
+    ("ctx.condition", "条件求值失败:", "Condition failed:"),
+    ("ctx.param", "参数", "parameter"),
+    ("ctx.var", "变量", "variable"),
+    // 全角符号的名称
+    ("punct.colon", "全角冒号", "Full-width colon"),
+    ("punct.comma", "全角逗号", "Full-width comma"),
+    ("punct.lparen", "全角左括号", "Full-width left parenthesis"),
+    ("punct.rparen", "全角右括号", "Full-width right parenthesis"),
+    ("punct.lquote", "中文左引号", "Chinese left quotation mark"),
+    ("punct.rquote", "中文右引号", "Chinese right quotation mark"),
+    ("punct.equals", "全角等号", "Full-width equals sign"),
+    ("punct.lbracket", "全角左方括号", "Full-width left bracket"),
+    ("punct.rbracket", "全角右方括号", "Full-width right bracket"),
+    ("punct.lbrace", "全角左花括号", "Full-width left brace"),
+    ("punct.rbrace", "全角右花括号", "Full-width right brace"),
+    ("punct.plus", "全角加号", "Full-width plus sign"),
+    ("punct.minus", "全角减号", "Full-width minus sign"),
+    ("punct.star", "全角星号", "Full-width asterisk"),
+    ("punct.slash", "全角斜杠", "Full-width slash"),
+    ("punct.percent", "全角百分号", "Full-width percent sign"),
+    ("punct.less", "全角小于号", "Full-width less-than sign"),
+    ("punct.greater", "全角大于号", "Full-width greater-than sign"),
+    ("punct.bang", "全角感叹号", "Full-width exclamation mark"),
+    ("punct.period", "全角句点", "Full-width full stop"),
+    ("punct.space", "全角空格", "Full-width space"),
+    // 命令行提示
+    ("ui.stack", "调用栈（最近的调用在前）:", "Call stack (most recent call first):"),
+    ("ui.frame", "  在 {}", "  at {}"),
+    ("ui.no_file", "请提供一个.hc文件", "Please provide a .hc file"),
+    ("ui.tab_width", "--tab-width 需要一个正整数: {}", "--tab-width needs a positive integer: {}"),
+    ("ui.keywords", "--keywords 只能是 zh 或 en: {}", "--keywords must be zh or en: {}"),
+    ("ui.keywords_fix", "--keywords 只能和 --fix 一起使用", "--keywords can only be used with --fix"),
+    ("ui.lang", "--lang 只能是 zh-CN 或 en-US: {}", "--lang must be zh-CN or en-US: {}"),
+    ("ui.unknown_option", "未知选项: {}", "Unknown option: {}"),
+    ("ui.extra_arg", "多余的参数: {}", "Unexpected argument: {}"),
+    ("ui.fix_none", "没有需要修改的内容: {}", "Nothing to fix: {}"),
+    ("ui.fix_done", "已改写 {} 行: {}", "Rewrote {} lines: {}"),
+    ("ui.check_failed", "检查发现 {} 个错误", "Check found {} error(s)"),
+    ("ui.check_passed", "检查通过: {}", "Check passed: {}"),
+    ("ui.doc_none", "没有定义函数: {}", "No functions defined: {}"),
+    ("ui.unknown_code", "未知的错误码: {}（运行 hitcode explain 列出所有错误码）", "Unknown error code: {} (run hitcode explain to list all codes)"),
+    ("ui.explain_one", "运行 hitcode explain {} 查看这个错误的详细说明", "For more information about this error, try hitcode explain {}"),
+    ("ui.explain_many", "这些错误有详细说明: {}；运行 hitcode explain 错误码 查看，如 hitcode explain {}", "Some errors have detailed explanations: {}. For more information about an error, try hitcode explain {}"),
+    // hitcode explain
+    ("explain.bad", "错误的写法:", "Erroneous example:"),
+    ("explain.good", "正确的写法:", "Corrected example:"),
+    ("explain.category", "{0} 是{1}的通用错误码。脚本用 throw Error(kind: \"{2}\", message: \"...\") 抛出这一类错误，或在 catch 中用 throw 重新抛出捕获到的{1}时使用它；解释器自己发现的{1}使用下面更具体的错误码:", "{0} is the generic code for {1}. It is used when a script raises this kind of error itself with throw Error(kind: \"{2}\", message: \"...\") or rethrows a caught one in catch; {1} found by the interpreter use the more specific codes below:"),
+    ("category.E0001", "语法错误", "syntax errors"),
+    ("category.E0100", "类型错误", "type errors"),
+    ("category.E0200", "名称错误", "name errors"),
+    ("category.E0300", "值错误", "value errors"),
+    ("category.E0400", "下标错误", "index errors"),
+    ("category.E0500", "键错误", "key errors"),
+    ("category.E0600", "除零错误", "division-by-zero errors"),
+    ("category.E0700", "输入输出错误", "I/O errors"),
+];
+
+// 按当前语言取出消息并填入参数；目录中没有的键原样返回
+fn tr(key: &str, args: &[&dyn fmt::Display]) -> String {
+    tr_in(lang(), key, args)
+}
+
+// 按指定的语言取出消息并填入参数
+fn tr_in(lang: Lang, key: &str, args: &[&dyn fmt::Display]) -> String {
+    let Some(&(_, zh, en)) = MESSAGES.iter().find(|(k, _, _)| *k == key) else { return key.to_string() };
+    let template = match lang {
+        Lang::ZhCn => zh,
+        Lang::EnUs => en,
+    };
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let close = rest.find('}').unwrap_or(rest.len() - 1);
+        let index = &rest[1..close];
+        let arg = if index.is_empty() {
+            next += 1;
+            args.get(next - 1)
+        } else {
+            index.parse::<usize>().ok().and_then(|i| args.get(i))
+        };
+        match arg {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str(&rest[..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// 错误码的详细说明，用 hitcode explain 错误码 查看；示例是完整的程序或命令行
+struct Explanation {
+    code: &'static str,
+    zh: &'static str,
+    en: &'static str,
+    // 会报告这个错误码的写法和改正后的写法
+    bad: &'static str,
+    good: &'static str,
+}
+
+const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0002",
+        zh: "var 声明的写法不对。变量声明写作 var 类型 名字 = 初始值，类型可以省略（var 名字 = 初始值，由初始值推断类型）。常见的原因是漏写了 = 和初始值、类型名拼错，或者用了没有定义的结构体、枚举作为类型。",
+        en: "A var declaration is malformed. A variable is declared as var type name = value; the type may be omitted (var name = value) and is then inferred from the value. Common causes are a missing = and initial value, a misspelled type name, or a struct or enum type that is not defined.",
+        bad: "start:\n    var int count\nend()",
+        good: "start:\n    var int count = 0\n    say count\nend()",
+    },
+    Explanation {
+        code: "E0003",
+        zh: "const 声明的写法不对。常量必须写明类型并给出初始值：const 类型 名字 = 值。常量没有“先声明、后赋值”的写法，也不能省略类型。",
+        en: "A const declaration is malformed. A constant must state its type and have an initial value: const type name = value. Constants cannot be declared first and assigned later, and the type cannot be omitted.",
+        bad: "start:\n    const LIMIT = 10\nend()",
+        good: "start:\n    const int LIMIT = 10\n    say LIMIT\nend()",
+    },
+    Explanation {
+        code: "E0004",
+        zh: "list、map、set 或 deque 声明中的类型不对。集合声明写作 list 元素类型 名字 = [...]、map 键类型 值类型 名字 = {...}、set 元素类型 名字 = {...}、deque 元素类型 名字 = [...]；元素类型必须是内置类型或已经定义的结构体、枚举。",
+        en: "The type in a list, map, set or deque declaration is not valid. Collections are declared as list elem_type name = [...], map key_type value_type name = {...}, set elem_type name = {...} and deque elem_type name = [...]; element types must be built-in types or defined structs and enums.",
+        bad: "struct Point: int x, int y\n\nstart:\n    list Pointt points = []\nend()",
+        good: "struct Point: int x, int y\n\nstart:\n    list Point points = []\n    say points\nend()",
+    },
+    Explanation {
+        code: "E0005",
+        zh: "这个错误码已经不再使用。早期版本中 say 后面不能直接写函数调用，必须写 say call add(1, 2)；现在函数调用是普通的表达式，say add(1, 2) 可以直接使用，写 call 也仍然有效。",
+        en: "This error code is no longer emitted. Earlier versions did not allow a function call directly after say and required say call add(1, 2); function calls are now ordinary expressions, so say add(1, 2) works, and a leading call is still accepted.",
+        bad: "",
+        good: "",
+    },
+    Explanation {
+        code: "E0006",
+        zh: "for 循环的写法不对。for 写作 for 名字 in 集合:，遍历字典时写 for 键, 值 in 字典:。常见的原因是漏写了 in，或者对列表写了两个循环变量。",
+        en: "A for loop is malformed. A for loop is written for name in collection:, and a map is iterated with for key, value in map:. Common causes are a missing in, or two loop variables over a list.",
+        bad: "start:\n    list int xs = [1, 2]\n    for x, y in xs:\n        say x\nend()",
+        good: "start:\n    list int xs = [1, 2]\n    for x in xs:\n        say x\nend()",
+    },
+    Explanation {
+        code: "E0007",
+        zh: "try 块后面没有 catch 或 finally。try 块必须紧跟至少一个 catch 块或 finally 块，它们与 try 写在同一缩进。只想忽略错误时也要写一个 catch: 块。",
+        en: "A try block is not followed by catch or finally. A try block must be followed by at least one catch or finally block at the same indentation. To ignore an error, still write a catch: block.",
+        bad: "start:\n    try:\n        say 1 / 0\n    say \"done\"\nend()",
+        good: "start:\n    try:\n        say 1 / 0\n    catch err:\n        say err.message\n    say \"done\"\nend()",
+    },
+    Explanation {
+        code: "E0008",
+        zh: "catch 或 finally 前面没有 try 块。catch 和 finally 必须紧跟在 try 块之后，并与 try 写在同一缩进；缩进不对齐时它们不会被当作 try 的一部分。",
+        en: "A catch or finally block does not follow a try block. catch and finally must come right after a try block, at the same indentation; if the indentation differs they are not treated as part of the try.",
+        bad: "start:\n    say \"start\"\n    catch:\n        say \"failed\"\nend()",
+        good: "start:\n    try:\n        say \"start\"\n    catch:\n        say \"failed\"\nend()",
+    },
+    Explanation {
+        code: "E0009",
+        zh: "只写了函数名，没有加括号。只写函数名不会执行函数；调用函数要写 函数名(参数)，没有参数时也要写括号，如 main_menu()。前面也可以加上 call，如 call greet(\"Bob\")。",
+        en: "A function name is written on its own without parentheses. Writing just the name does not run the function; call it as name(args), with empty parentheses when there are no arguments, e.g. main_menu(). A leading call is also accepted, e.g. call greet(\"Bob\").",
+        bad: "function hello():\n    say \"hello\"\n    end()\n\nstart:\n    hello\nend()",
+        good: "function hello():\n    say \"hello\"\n    end()\n\nstart:\n    hello()\nend()",
+    },
+    Explanation {
+        code: "E0010",
+        zh: "这里需要一个表达式，但什么也没有写。常见的原因是 = 右边空着，或者运算符后面漏了操作数。",
+        en: "An expression is required here but nothing was written. Common causes are an empty right-hand side of = or a missing operand after an operator.",
+        bad: "start:\n    var int x = 1\n    x = \nend()",
+        good: "start:\n    var int x = 1\n    x = 2\n    say x\nend()",
+    },
+    Explanation {
+        code: "E0011",
+        zh: "常量的初始值在程序运行之前就要确定，只能使用字面量、其他常量和运算，不能使用变量、函数调用和 input()。需要运行时才能算出的值请用 var 声明。",
+        en: "The initial value of a constant is fixed before the program runs, so it may only use literals, other constants and operators, not variables, function calls or input(). Use var for values that are only known at run time.",
+        bad: "start:\n    var int base = 10\n    const int LIMIT = base * 2\nend()",
+        good: "start:\n    const int BASE = 10\n    const int LIMIT = BASE * 2\n    say LIMIT\nend()",
+    },
+    Explanation {
+        code: "E0012",
+        zh: "函数没有以 end(...) 结束。每个 function 都要在函数体之后写 end(返回值)，没有返回值时写 end()；否则后面的定义或 start: 会被当作函数体的一部分。",
+        en: "A function is not closed with end(...). Every function body must end with end(value), or end() when nothing is returned; otherwise the following definitions or start: would be read as part of the function.",
+        bad: "function hello():\n    say \"hello\"\n\nstart:\n    call hello()\nend()",
+        good: "function hello():\n    say \"hello\"\n    end()\n\nstart:\n    call hello()\nend()",
+    },
+    Explanation {
+        code: "E0013",
+        zh: "start: 块没有以 end() 结束。主程序块写作 start: ... end()，end() 之后才能再写函数、结构体或枚举的定义。",
+        en: "The start: block is not closed with end(). The main block is written start: ... end(), and function, struct or enum definitions may only follow after end().",
+        bad: "start:\n    say \"hi\"\n\nstruct Point: int x, int y",
+        good: "start:\n    say \"hi\"\nend()\n\nstruct Point: int x, int y",
+    },
+    Explanation {
+        code: "E0014",
+        zh: "程序中有两个 start: 块。一个程序只能有一个主程序块，请把两个块中的语句合并到一个 start: 中。",
+        en: "The program has two start: blocks. A program has exactly one main block; merge the statements into a single start:.",
+        bad: "start:\n    say \"one\"\nend()\n\nstart:\n    say \"two\"\nend()",
+        good: "start:\n    say \"one\"\n    say \"two\"\nend()",
+    },
+    Explanation {
+        code: "E0015",
+        zh: "语句写在了所有 function 和 start: 块之外。顶层只能写函数、结构体、枚举的定义以及 var、const 和集合声明，其他语句要放进 start: 块或函数中。",
+        en: "A statement is written outside every function and start: block. Only function, struct and enum definitions and var, const and collection declarations may appear at the top level; other statements belong in start: or a function.",
+        bad: "say \"hi\"\n\nstart:\n    say \"there\"\nend()",
+        good: "start:\n    say \"hi\"\n    say \"there\"\nend()",
+    },
+    Explanation {
+        code: "E0016",
+        zh: "程序没有 start: 块。start: 块是程序开始执行的地方，每个程序都必须有一个。",
+        en: "The program has no start: block. Execution begins in the start: block, so every program needs one.",
+        bad: "function hello():\n    say \"hello\"\n    end()",
+        good: "function hello():\n    say \"hello\"\n    end()\n\nstart:\n    call hello()\nend()",
+    },
+    Explanation {
+        code: "E0017",
+        zh: "同一行的缩进中既有 Tab 又有空格。Tab 的宽度在不同编辑器中不一样，混用时看起来对齐的代码可能属于不同的代码块。请统一用空格（或统一用 Tab）缩进；确实需要混用时，可以用 --tab-width N 指定 Tab 的宽度。",
+        en: "The indentation of a line mixes tabs and spaces. Tabs have different widths in different editors, so code that looks aligned may belong to different blocks. Indent with spaces only (or tabs only); if mixing is intended, set the tab width with --tab-width N.",
+        bad: "start:\n\t    say \"hi\"\nend()",
+        good: "start:\n    say \"hi\"\nend()",
+    },
+    Explanation {
+        code: "E0018",
+        zh: "这一行用 Tab 缩进，而文件中更早的行用空格缩进。一个文件只能使用一种缩进字符；请把这一行的 Tab 换成空格，或者用 --tab-width N 指定 Tab 的宽度。",
+        en: "This line is indented with tabs while an earlier line uses spaces. A file may use only one indentation character; replace the tabs with spaces, or set the tab width with --tab-width N.",
+        bad: "start:\n    say \"one\"\n\tsay \"two\"\nend()",
+        good: "start:\n    say \"one\"\n    say \"two\"\nend()",
+    },
+    Explanation {
+        code: "E0019",
+        zh: "这一行用空格缩进，而文件中更早的行用 Tab 缩进。一个文件只能使用一种缩进字符；请把这一行的空格换成 Tab，或者用 --tab-width N 指定 Tab 的宽度。",
+        en: "This line is indented with spaces while an earlier line uses tabs. A file may use only one indentation character; replace the spaces with tabs, or set the tab width with --tab-width N.",
+        bad: "start:\n\tsay \"one\"\n    say \"two\"\nend()",
+        good: "start:\n\tsay \"one\"\n\tsay \"two\"\nend()",
+    },
+    Explanation {
+        code: "E0020",
+        zh: "这一行比上一行缩进得更深，但上一行没有以 : 结尾。只有 if、while、for、函数等以 : 结尾的行后面才能开始一个更深的代码块；多出来的缩进通常是不小心加上的。",
+        en: "This line is indented further than the previous one, but the previous line does not end with :. Only lines ending with : (if, while, for, functions, ...) can open a deeper block; the extra indentation is usually accidental.",
+        bad: "start:\n    say \"one\"\n        say \"two\"\nend()",
+        good: "start:\n    say \"one\"\n    say \"two\"\nend()",
+    },
+    Explanation {
+        code: "E0021",
+        zh: "上一行以 : 结尾，开启了一个代码块，但这一行没有缩进得更深。if、while、for 等语句的代码块要比它们多缩进一级，代码块不能为空。",
+        en: "The previous line ends with : and opens a block, but this line is not indented further. The body of if, while, for and similar statements must be indented one level deeper, and it cannot be empty.",
+        bad: "start:\n    var int x = 1\n    if x > 0:\n    say \"positive\"\nend()",
+        good: "start:\n    var int x = 1\n    if x > 0:\n        say \"positive\"\nend()",
+    },
+    Explanation {
+        code: "E0022",
+        zh: "这一行减少了缩进，但没有回到任何外层代码块的缩进位置。减少缩进时必须与某个外层语句对齐，例如回到 if 所在的缩进。",
+        en: "This line is dedented, but not to the indentation of any enclosing block. When dedenting, a line must line up with an outer statement, e.g. the if it belongs to.",
+        bad: "start:\n    var int x = 1\n    if x > 0:\n            say \"positive\"\n        say \"done\"\nend()",
+        good: "start:\n    var int x = 1\n    if x > 0:\n        say \"positive\"\n    say \"done\"\nend()",
+    },
+    Explanation {
+        code: "E0023",
+        zh: "文件开头的关键字指示给出了未知的关键字集合。# keywords: 后面只能写 zh（中文关键字）或 en（英文关键字），也可以写作 # 关键字: 中文。",
+        en: "The keyword pragma at the top of the file names an unknown keyword set. # keywords: accepts only zh (Chinese keywords) or en (English keywords); it may also be written # 关键字: 中文.",
+        bad: "# keywords: cn\nstart:\n    say \"hi\"\nend()",
+        good: "# keywords: en\nstart:\n    say \"hi\"\nend()",
+    },
+    Explanation {
+        code: "E0024",
+        zh: "字符串字面量没有结束：开头的双引号之后直到行尾都没有找到结尾的双引号。字符串中要写双引号时用 \\\" 转义；普通字符串不能跨行，需要多行文字时用三引号字符串 \"\"\"...\"\"\"。",
+        en: "A string literal is not terminated: no closing double quote was found before the end of the line. Write a double quote inside a string as \\\"; ordinary strings cannot span lines, use a triple-quoted string \"\"\"...\"\"\" for multi-line text.",
+        bad: "start:\n    say \"hello\nend()",
+        good: "start:\n    say \"hello\"\nend()",
+    },
+    Explanation {
+        code: "E0025",
+        zh: "三引号字符串 \"\"\"...\"\"\" 可以跨多行，但直到文件末尾都没有找到结尾的 \"\"\"。请在字符串内容结束的地方补上三个双引号。",
+        en: "A triple-quoted string \"\"\"...\"\"\" may span several lines, but no closing \"\"\" was found before the end of the file. Add three double quotes where the text ends.",
+        bad: "start:\n    var s = \"\"\"first line\n    second line\n    say s\nend()",
+        good: "start:\n    var s = \"\"\"first line\n    second line\"\"\"\n    say s\nend()",
+    },
+    Explanation {
+        code: "E0026",
+        zh: "字符串中的反斜杠后面跟了不认识的字符。支持的转义只有 \\n（换行）、\\t（Tab）、\\\"（双引号）、\\\\（反斜杠）和 \\u{...}（Unicode 字符）。要原样写出反斜杠，例如 Windows 路径，可以写成 \\\\，或者使用原始字符串 r\"...\"，其中的反斜杠不做转义。",
+        en: "A backslash in a string is followed by an unsupported character. The only escapes are \\n (newline), \\t (tab), \\\" (double quote), \\\\ (backslash) and \\u{...} (Unicode character). To write a literal backslash, e.g. in a Windows path, use \\\\ or a raw string r\"...\", in which backslashes are not escapes.",
+        bad: "start:\n    say \"C:\\data\\hc\"\nend()",
+        good: "start:\n    say r\"C:\\data\\hc\"\nend()",
+    },
+    Explanation {
+        code: "E0027",
+        zh: "\\u 转义的写法不对。Unicode 转义写作 \\u{码点}，花括号中是 1 到 6 位十六进制数，并且必须是有效的 Unicode 字符（不超过 10FFFF，也不能是代理码点 D800 到 DFFF）。",
+        en: "A \\u escape is malformed. A Unicode escape is written \\u{code point} with 1 to 6 hex digits in the braces, and it must be a valid Unicode character (at most 10FFFF and not a surrogate D800 to DFFF).",
+        bad: "start:\n    say \"\\u{110000}\"\nend()",
+        good: "start:\n    say \"\\u{4F60}\\u{597D}\"\nend()",
+    },
+    Explanation {
+        code: "E0028",
+        zh: "块注释 /* ... */ 可以跨多行，但直到文件末尾都没有找到结尾的 */。请在注释结束的地方补上 */；只注释一行时也可以用 # 或 //。",
+        en: "A block comment /* ... */ may span several lines, but no closing */ was found before the end of the file. Add */ where the comment ends; for a single line, # or // also works.",
+        bad: "start:\n    /* 打招呼\n    say \"hi\"\nend()",
+        good: "start:\n    /* 打招呼 */\n    say \"hi\"\nend()",
+    },
+    Explanation {
+        code: "E0029",
+        zh: "无法识别的语句：它不是声明、赋值、say、函数调用或任何控制结构。常见原因是关键字拼错（如把 say 写成 sya）或者漏写了 = 、括号。请检查这一行的写法。",
+        en: "A line is not a declaration, assignment, say, function call or any control structure. This is usually a misspelled keyword (e.g. sya instead of say) or a missing = or parentheses. Check how the line is written.",
+        bad: "start:\n    sya \"hi\"\nend()",
+        good: "start:\n    say \"hi\"\nend()",
+    },
+    Explanation {
+        code: "E0101",
+        zh: "值的类型与声明的类型不一致。变量、常量、集合元素、结构体字段和函数参数都有确定的类型，赋给它们的值必须是这个类型；int 可以赋给 double，其他类型之间不会自动转换。",
+        en: "A value does not have the declared type. Variables, constants, collection elements, struct fields and function parameters all have a fixed type, and values assigned to them must have that type; an int may be used as a double, but no other conversions happen automatically.",
+        bad: "start:\n    var int age = \"ten\"\nend()",
+        good: "start:\n    var int age = 10\n    say age\nend()",
+    },
+    Explanation {
+        code: "E0102",
+        zh: "把 none 赋给了不是 opt 类型的变量、参数或字段。只有声明为 opt 类型（如 opt int）的名字才能为 none；可能没有值的地方请声明为 opt 类型，使用前用 if 名字 is not none: 检查。",
+        en: "none is assigned to a variable, parameter or field that is not an opt type. Only names declared with an opt type (e.g. opt int) can be none; declare values that may be missing as opt and check them with if name is not none: before use.",
+        bad: "start:\n    var int best = none\nend()",
+        good: "start:\n    var opt int best = none\n    if best is not none:\n        say best\nend()",
+    },
+    Explanation {
+        code: "E0103",
+        zh: "放进集合的元素与集合的元素类型不一致。list int 只能放 int，set str 只能放 str，依此类推；列表字面量中的每个元素都要符合类型。",
+        en: "An element put into a collection does not have the collection's element type. A list int holds only ints, a set str only strs, and so on; every element of a literal must match.",
+        bad: "start:\n    list int scores = [90, \"A\"]\nend()",
+        good: "start:\n    list int scores = [90, 85]\n    say scores\nend()",
+    },
+    Explanation {
+        code: "E0104",
+        zh: "省略类型声明变量时，无法从初始值推断出类型，例如初始值是 none。请写明变量的类型，如 var opt int 名字 = none。",
+        en: "The type of a variable declared without a type cannot be inferred from its initial value, e.g. when the value is none. Write the type explicitly, e.g. var opt int name = none.",
+        bad: "start:\n    var best = none\nend()",
+        good: "start:\n    var opt int best = none\n    say best\nend()",
+    },
+    Explanation {
+        code: "E0105",
+        zh: "解构赋值右边的值不是元素个数相同的元组。var (类型 名字, ...) = 值 要求值是一个元组，并且元素个数与左边的名字个数相同。",
+        en: "The right-hand side of a destructuring declaration is not a tuple with the same number of elements. var (type name, ...) = value requires a tuple whose length matches the names on the left.",
+        bad: "start:\n    var (int q, int r) = (1, 2, 3)\nend()",
+        good: "start:\n    var (int q, int r) = (1, 2)\n    say q\nend()",
+    },
+    Explanation {
+        code: "E0106",
+        zh: "函数 end(...) 返回的值与 ->return:: 声明的返回类型不一致。请检查 end(...) 中的表达式，或者修改函数声明的返回类型。",
+        en: "The value returned by end(...) does not match the return type declared with ->return::. Check the expression in end(...), or change the declared return type.",
+        bad: "function half(int n)->return::int:\n    end(\"half\")\n\nstart:\n    var int n = call half(3)\nend()",
+        good: "function half(int n)->return::int:\n    end(n / 2)\n\nstart:\n    var int n = call half(6)\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0107",
+        zh: "调用函数时给出的参数个数与函数定义不一致。调用时的参数要与 function 名字(...) 中声明的参数一一对应。",
+        en: "A function is called with the wrong number of arguments. The arguments must match the parameters declared in function name(...) one to one.",
+        bad: "function add(int a, int b)->return::int:\n    end(a + b)\n\nstart:\n    var int n = call add(1)\nend()",
+        good: "function add(int a, int b)->return::int:\n    end(a + b)\n\nstart:\n    var int n = call add(1, 2)\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0108",
+        zh: "构造结构体或枚举成员时给出的值比字段多。按位置构造时值的个数不能超过定义中的字段个数。",
+        en: "More values are given than the struct or enum variant has fields. When constructing by position, the number of values cannot exceed the number of fields.",
+        bad: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(1, 2, 3)\nend()",
+        good: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(1, 2)\n    say p\nend()",
+    },
+    Explanation {
+        code: "E0109",
+        zh: "给不带载荷的枚举成员传入了值。只有定义时写了字段的成员（如 Circle(double r)）才能带值构造，其他成员直接写 枚举.成员。",
+        en: "Values are passed to an enum variant without a payload. Only variants defined with fields (e.g. Circle(double r)) take values; other variants are written as Enum.Variant.",
+        bad: "enum Shape: Empty, Circle(double r)\n\nstart:\n    var Shape s = Shape.Empty(1)\nend()",
+        good: "enum Shape: Empty, Circle(double r)\n\nstart:\n    var Shape s = Shape.Empty\n    say s\nend()",
+    },
+    Explanation {
+        code: "E0110",
+        zh: "对不是列表或字典的值使用了下标。只有列表（xs[0]）、字典（m[\"key\"]）和元组可以用 [...] 访问。",
+        en: "An index is used on a value that is not a list or map. Only lists (xs[0]), maps (m[\"key\"]) and tuples can be accessed with [...].",
+        bad: "start:\n    var int n = 5\n    say n[0]\nend()",
+        good: "start:\n    list int xs = [5]\n    say xs[0]\nend()",
+    },
+    Explanation {
+        code: "E0111",
+        zh: "对不是列表或字典的值做了下标赋值。xs[i] = 值 只能用于列表和字典。",
+        en: "An indexed assignment targets a value that is not a list or map. xs[i] = value only works on lists and maps.",
+        bad: "start:\n    var int n = 5\n    n[0] = 1\nend()",
+        good: "start:\n    list int xs = [5]\n    xs[0] = 1\n    say xs\nend()",
+    },
+    Explanation {
+        code: "E0112",
+        zh: "静态检查发现对不能下标访问的类型使用了下标。只有列表、字典和元组可以用 [...] 访问。",
+        en: "Static checking found an index on a type that cannot be indexed. Only lists, maps and tuples support [...].",
+        bad: "start:\n    var str s = \"abc\"\n    var str c = s[0]\nend()",
+        good: "start:\n    list str cs = [\"a\", \"b\", \"c\"]\n    var str c = cs[0]\n    say c\nend()",
+    },
+    Explanation {
+        code: "E0113",
+        zh: "字典的键类型不对。map str int 的键只能是 str，访问或赋值时使用的键也要符合字典声明的键类型。",
+        en: "A map key has the wrong type. The keys of a map str int must be strs, and the keys used for lookup or assignment must match the declared key type.",
+        bad: "start:\n    map str int ages = {\"Ann\": 30}\n    ages[1] = 31\nend()",
+        good: "start:\n    map str int ages = {\"Ann\": 30}\n    ages[\"Ann\"] = 31\n    say ages\nend()",
+    },
+    Explanation {
+        code: "E0114",
+        zh: "对不是集合的值调用了 len()。len() 只能用于列表、字典、集合和双端队列。",
+        en: "len() is called on a value that is not a collection. len() works on lists, maps, sets and deques.",
+        bad: "start:\n    var int n = 5\n    say n.len()\nend()",
+        good: "start:\n    list int xs = [5]\n    say xs.len()\nend()",
+    },
+    Explanation {
+        code: "E0115",
+        zh: "对不支持 has 的值调用了 has()。has(x) 用于字典（是否有键 x）和集合（是否有元素 x）；列表请用 for 遍历查找。",
+        en: "has() is called on a value that does not support it. has(x) works on maps (is x a key) and sets (is x an element); search a list with a for loop.",
+        bad: "start:\n    var str s = \"abc\"\n    say s.has(\"a\")\nend()",
+        good: "start:\n    set str s = {\"a\", \"b\"}\n    say s.has(\"a\")\nend()",
+    },
+    Explanation {
+        code: "E0116",
+        zh: "集合运算 union、intersection、difference 的参数不是集合或列表。",
+        en: "The argument of the set operations union, intersection or difference is not a set or list.",
+        bad: "start:\n    set int a = {1, 2}\n    say a.union(3)\nend()",
+        good: "start:\n    set int a = {1, 2}\n    say a.union({3})\nend()",
+    },
+    Explanation {
+        code: "E0117",
+        zh: "修改容器的方法（add、remove、push_back、push_front、pop_back、pop_front）只能对声明过的集合变量调用，不能对字面量或表达式的结果调用，因为修改后的结果无处保存。",
+        en: "Methods that modify a container (add, remove, push_back, push_front, pop_back, pop_front) can only be called on declared collection variables, not on literals or expression results, since the change would be lost.",
+        bad: "start:\n    say {1, 2}.add(3)\nend()",
+        good: "start:\n    set int s = {1, 2}\n    s.add(3)\n    say s\nend()",
+    },
+    Explanation {
+        code: "E0118",
+        zh: "值不支持这个方法，或者参数个数不对。列表、字典、集合和双端队列各有自己的一组方法，请对照教程检查方法名和参数。",
+        en: "The value does not support this method, or the method got the wrong number of arguments. Lists, maps, sets and deques each have their own methods; check the name and arguments against the tutorial.",
+        bad: "start:\n    map str int ages = {\"Ann\": 30}\n    say ages.size()\nend()",
+        good: "start:\n    map str int ages = {\"Ann\": 30}\n    say ages.len()\nend()",
+    },
+    Explanation {
+        code: "E0119",
+        zh: "静态检查发现对某个类型调用了它不支持的方法，或者参数个数不对。",
+        en: "Static checking found a method that the value's type does not support, or a call with the wrong number of arguments.",
+        bad: "start:\n    list int xs = [1]\n    var int n = xs.size()\nend()",
+        good: "start:\n    list int xs = [1]\n    var int n = xs.len()\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0120",
+        zh: "算术运算的两边不都是数字。+ - * / % 只能用于 int 和 double；字符串不能做算术运算。",
+        en: "An arithmetic operand is not a number. + - * / % only work on int and double; strings cannot be used in arithmetic.",
+        bad: "start:\n    var str a = \"1\"\n    var int n = a + 1\nend()",
+        good: "start:\n    var int a = 1\n    var int n = a + 1\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0121",
+        zh: "静态检查发现参与算术运算的表达式不是数字类型（例如 str 或可能为 none 的 opt int）。可能为 none 的值要先用 if 名字 is not none: 检查。",
+        en: "Static checking found an arithmetic operand whose type is not numeric (e.g. str, or an opt int that may be none). Check values that may be none with if name is not none: first.",
+        bad: "start:\n    var opt int a = 1\n    var int n = a + 1\nend()",
+        good: "start:\n    var opt int a = 1\n    if a is not none:\n        var int n = a + 1\n        say n\nend()",
+    },
+    Explanation {
+        code: "E0122",
+        zh: "复合赋值（+=、-=、*=、/=、%=）右边的值不是数字。",
+        en: "The right-hand side of a compound assignment (+=, -=, *=, /=, %=) is not a number.",
+        bad: "start:\n    var int n = 1\n    n += \"2\"\nend()",
+        good: "start:\n    var int n = 1\n    n += 2\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0123",
+        zh: "值为 none 的变量被用在了需要实际值的地方，例如参与运算。使用 opt 类型的变量之前，先用 if 名字 is not none: 检查。",
+        en: "A variable whose value is none is used where a real value is needed, e.g. in arithmetic. Check opt variables with if name is not none: before using them.",
+        bad: "start:\n    var opt int a = none\n    var opt int b = a\n    var int n = 0\n    n = b + 1\nend()",
+        good: "start:\n    var opt int a = none\n    var int n = 0\n    if a is not none:\n        n = a + 1\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0124",
+        zh: "对值为 none 的变量做了复合赋值（如 +=）。先用 if 名字 is not none: 检查；变量总是有值时，不要把它声明为 opt 类型。",
+        en: "A compound assignment (such as +=) is applied to a variable whose value is none. Check it with if name is not none: first; if the variable always has a value, do not declare it as opt.",
+        bad: "start:\n    var opt int total = none\n    total += 1\nend()",
+        good: "start:\n    var opt int total = none\n    if total is not none:\n        total += 1\n    say total\nend()",
+    },
+    Explanation {
+        code: "E0125",
+        zh: "静态检查发现访问了可能为 none 的值的字段。先用 if 名字 is not none: 检查，检查过的块中它的类型就不再是 opt。",
+        en: "Static checking found a field access on a value that may be none. Check it with if name is not none: first; inside that block its type is no longer opt.",
+        bad: "struct Point: int x, int y\n\nstart:\n    var opt Point p = Point(1, 2)\n    var int x = p.x\nend()",
+        good: "struct Point: int x, int y\n\nstart:\n    var opt Point p = Point(1, 2)\n    if p is not none:\n        var int x = p.x\n        say x\nend()",
+    },
+    Explanation {
+        code: "E0126",
+        zh: "静态检查发现对可能为 none 的值使用了下标。先用 if 名字 is not none: 检查。",
+        en: "Static checking found an index on a value that may be none. Check it with if name is not none: first.",
+        bad: "start:\n    var opt (int, int) t = (1, 2)\n    var int a = t[0]\nend()",
+        good: "start:\n    var opt (int, int) t = (1, 2)\n    if t is not none:\n        var int a = t[0]\n        say a\nend()",
+    },
+    Explanation {
+        code: "E0127",
+        zh: "静态检查发现对可能为 none 的值调用了方法。先用 if 名字 is not none: 检查。",
+        en: "Static checking found a method call on a value that may be none. Check it with if name is not none: first.",
+        bad: "start:\n    var opt list int xs = [1]\n    var int n = xs.len()\nend()",
+        good: "start:\n    var opt list int xs = [1]\n    if xs is not none:\n        var int n = xs.len()\n        say n\nend()",
+    },
+    Explanation {
+        code: "E0128",
+        zh: "用 >、<、>=、<= 比较了 none。none 没有大小，只能用 == 或 != 比较，或者写 is none / is not none。",
+        en: "none is compared with >, <, >= or <=. none has no order; it can only be compared with == or !=, or tested with is none / is not none.",
+        bad: "start:\n    var opt int a = none\n    if a > 0:\n        say a\nend()",
+        good: "start:\n    var opt int a = none\n    if a is not none:\n        if a > 0:\n            say a\nend()",
+    },
+    Explanation {
+        code: "E0129",
+        zh: "对不是结构体的值使用了 .字段。只有结构体的值（如 Point(x: 1, y: 2)）才有字段。",
+        en: "A .field access is used on a value that is not a struct. Only struct values (such as Point(x: 1, y: 2)) have fields.",
+        bad: "start:\n    var int n = 5\n    say n.x\nend()",
+        good: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(5, 6)\n    say p.x\nend()",
+    },
+    Explanation {
+        code: "E0130",
+        zh: "修改了常量。const 声明的名字在整个程序中都不能再赋值，也不能修改它的元素或字段；需要修改的值请用 var 声明。",
+        en: "A constant is modified. A name declared with const can never be assigned again, nor can its elements or fields be changed; use var for values that change.",
+        bad: "start:\n    const int LIMIT = 10\n    LIMIT = 20\nend()",
+        good: "start:\n    var int limit = 10\n    limit = 20\n    say limit\nend()",
+    },
+    Explanation {
+        code: "E0131",
+        zh: "函数参数是 list 类型，但调用时传入的值不是列表。静态检查会把这种调用报告为 E0101。",
+        en: "A list parameter receives a value that is not a list. Static checking reports such a call as E0101.",
+        bad: "function show(list int xs):\n    say xs\n    end()\n\nstart:\n    call show(5)\nend()",
+        good: "function show(list int xs):\n    say xs\n    end()\n\nstart:\n    call show([5])\nend()",
+    },
+    Explanation {
+        code: "E0132",
+        zh: "函数参数是 map 类型，但调用时传入的值不是字典。静态检查会把这种调用报告为 E0101。",
+        en: "A map parameter receives a value that is not a map. Static checking reports such a call as E0101.",
+        bad: "function show(map str int xs):\n    say xs\n    end()\n\nstart:\n    call show(5)\nend()",
+        good: "function show(map str int xs):\n    say xs\n    end()\n\nstart:\n    call show({\"a\": 5})\nend()",
+    },
+    Explanation {
+        code: "E0133",
+        zh: "函数参数是 set 类型，但调用时传入的值不是集合。集合可以用集合字面量 {1, 2} 或列表 [1, 2] 初始化。静态检查会把这种调用报告为 E0101。",
+        en: "A set parameter receives a value that is not a set. A set can be initialized from a set literal {1, 2} or a list [1, 2]. Static checking reports such a call as E0101.",
+        bad: "function show(set int xs):\n    say xs\n    end()\n\nstart:\n    call show(5)\nend()",
+        good: "function show(set int xs):\n    say xs\n    end()\n\nstart:\n    call show({5})\nend()",
+    },
+    Explanation {
+        code: "E0134",
+        zh: "throw 后面的值不是字符串，也不是 Error 结构体。请写 throw \"消息\"，或 throw Error(kind: \"类别\", message: \"消息\")。",
+        en: "The value after throw is neither a string nor an Error struct. Write throw \"message\", or throw Error(kind: \"Kind\", message: \"message\").",
+        bad: "start:\n    throw 42\nend()",
+        good: "start:\n    try:\n        throw \"bad input\"\n    catch err:\n        say err.message\nend()",
+    },
+    Explanation {
+        code: "E0135",
+        zh: "exit(...) 的参数不是整数。退出码必须是整数，exit() 等同于 exit(0)。",
+        en: "The argument of exit(...) is not an integer. The exit code must be an integer; exit() is the same as exit(0).",
+        bad: "start:\n    exit(\"done\")\nend()",
+        good: "start:\n    exit(0)\nend()",
+    },
+    Explanation {
+        code: "E0136",
+        zh: "for 循环的 in 后面不是可以遍历的值。for 可以遍历列表、字典、集合和双端队列。",
+        en: "The value after in in a for loop cannot be iterated. for can iterate over lists, maps, sets and deques.",
+        bad: "start:\n    var int n = 3\n    for x in n:\n        say x\nend()",
+        good: "start:\n    list int xs = [1, 2, 3]\n    for x in xs:\n        say x\nend()",
+    },
+    Explanation {
+        code: "E0137",
+        zh: "case 范围 a..b 的边界不是数字。范围只能用于数字，例如 case 1..10:。",
+        en: "The bounds of a case range a..b are not numbers. Ranges only work with numbers, e.g. case 1..10:.",
+        bad: "start:\n    var int n = 3\n    switch n:\n        case \"a\"..5:\n            say \"low\"\nend()",
+        good: "start:\n    var int n = 3\n    switch n:\n        case 1..5:\n            say \"low\"\nend()",
+    },
+    Explanation {
+        code: "E0201",
+        zh: "使用了没有定义的名字。变量要先用 var（或 const、list 等）声明才能使用；也请检查名字是否拼错，以及它是否声明在当前函数可以看到的地方。",
+        en: "A name is used that is not defined. Variables must be declared with var (or const, list, ...) before use; also check the spelling and that the name is declared where the current function can see it.",
+        bad: "start:\n    say totl\nend()",
+        good: "start:\n    var int total = 1\n    say total\nend()",
+    },
+    Explanation {
+        code: "E0202",
+        zh: "给没有声明过的变量赋值。HitCode 中第一次出现的变量要用 var 声明，之后才能用 = 或 += 赋值；这样拼错的变量名会被发现，而不是悄悄创建一个新变量。",
+        en: "A value is assigned to a variable that has not been declared. A variable must be declared with var the first time it appears and can be assigned with = or += afterwards; this way a misspelled name is reported instead of silently creating a new variable.",
+        bad: "start:\n    count = 1\nend()",
+        good: "start:\n    var int count = 0\n    count = 1\n    say count\nend()",
+    },
+    Explanation {
+        code: "E0203",
+        zh: "调用了没有定义的函数。请检查函数名是否拼错，以及函数是否用 function 定义过。",
+        en: "A function is called that is not defined. Check the spelling and that the function is defined with function.",
+        bad: "function hello():\n    say \"hello\"\n    end()\n\nstart:\n    call helo()\nend()",
+        good: "function hello():\n    say \"hello\"\n    end()\n\nstart:\n    call hello()\nend()",
+    },
+    Explanation {
+        code: "E0204",
+        zh: "下标赋值的目标不是已经声明的列表、字典或变量。xs[i] = 值 之前要先用 list、map 或 var 声明 xs。静态检查会把它报告为 E0201。",
+        en: "The target of an indexed assignment is not a declared list, map or variable. Declare xs with list, map or var before xs[i] = value. Static checking reports this as E0201.",
+        bad: "start:\n    xs[0] = 1\nend()",
+        good: "start:\n    list int xs = [0]\n    xs[0] = 1\n    say xs\nend()",
+    },
+    Explanation {
+        code: "E0205",
+        zh: "say 后面的名字既不是已定义的变量，也不是字符串。要输出文字时请加上双引号：say \"hello\"；要输出变量时请先用 var 声明它。",
+        en: "The name after say is neither a defined variable nor a string. Put text in double quotes: say \"hello\"; declare a variable with var before printing it.",
+        bad: "start:\n    say hello\nend()",
+        good: "start:\n    say \"hello\"\nend()",
+    },
+    Explanation {
+        code: "E0206",
+        zh: "结构体没有这个字段。请对照 struct 定义检查字段名的拼写。",
+        en: "The struct has no such field. Check the field name against the struct definition.",
+        bad: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(1, 2)\n    say p.z\nend()",
+        good: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(1, 2)\n    say p.y\nend()",
+    },
+    Explanation {
+        code: "E0207",
+        zh: "构造结构体或枚举成员时缺少字段。每个字段都要给出值，按名字构造时写 名字(字段: 值, ...)。",
+        en: "A field is missing when constructing a struct or enum variant. Every field needs a value; when constructing by name write Name(field: value, ...).",
+        bad: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(x: 1)\nend()",
+        good: "struct Point: int x, int y\n\nstart:\n    var Point p = Point(x: 1, y: 2)\n    say p\nend()",
+    },
+    Explanation {
+        code: "E0208",
+        zh: "枚举没有这个成员。请对照 enum 定义检查成员名的拼写。",
+        en: "The enum has no such variant. Check the variant name against the enum definition.",
+        bad: "enum Color: Red, Green\n\nstart:\n    var Color c = Color.Purple\nend()",
+        good: "enum Color: Red, Green\n\nstart:\n    var Color c = Color.Red\n    say c\nend()",
+    },
+    Explanation {
+        code: "E0209",
+        zh: "在函数中用 var 重新声明了与参数同名的变量。参数本身就是函数中的变量，可以直接赋值，不需要再声明。",
+        en: "A variable with the same name as a parameter is declared again inside the function. Parameters already are variables in the function and can be assigned directly.",
+        bad: "function show(int n):\n    var int n = 2\n    say n\n    end()\n\nstart:\n    call show(1)\nend()",
+        good: "function show(int n):\n    n = 2\n    say n\n    end()\n\nstart:\n    call show(1)\nend()",
+    },
+    Explanation {
+        code: "E0210",
+        zh: "同一个名字在同一作用域中被声明了两次。第二次要改变值时用 = 赋值，而不是再写一次 var；确实需要另一个变量时请换个名字。",
+        en: "The same name is declared twice in one scope. To change the value the second time, assign with = instead of writing var again; use a different name if a second variable is really needed.",
+        bad: "start:\n    var int n = 1\n    var int n = 2\nend()",
+        good: "start:\n    var int n = 1\n    n = 2\n    say n\nend()",
+    },
+    Explanation {
+        code: "E0301",
+        zh: "列表的下标不是非负整数。列表下标从 0 开始，必须是 int，不能是负数、小数或字符串。",
+        en: "A list index is not a non-negative integer. List indices start at 0 and must be ints; negative numbers, decimals and strings are not allowed.",
+        bad: "start:\n    list int xs = [1, 2]\n    var int i = -1\n    say xs[i]\nend()",
+        good: "start:\n    list int xs = [1, 2]\n    var int i = 1\n    say xs[i]\nend()",
+    },
+    Explanation {
+        code: "E0302",
+        zh: "input() 读到的内容无法转换为变量的类型，例如 var int n = input() 时输入了字母。可以先读成 str，检查后再使用。",
+        en: "The text read by input() cannot be converted to the variable's type, e.g. letters typed for var int n = input(). Read it as a str first and validate it.",
+        bad: "start:\n    var int age = input(\"age: \")\nend()",
+        good: "start:\n    var str age = input(\"age: \")\n    say age\nend()",
+    },
+    Explanation {
+        code: "E0303",
+        zh: "用 to_int() 或 to_double() 转换的字符串不是有效的数字，例如 \"12a\".to_int()。转换前可以先用 trim() 去掉两端的空白；内容可能不是数字时，把转换放进 try 块，在 catch 中处理 ValueError。",
+        en: "A string converted with to_int() or to_double() is not a valid number, e.g. \"12a\".to_int(). Use trim() first to remove surrounding whitespace; when the text may not be a number, do the conversion in a try block and handle the ValueError in catch.",
+        bad: "start:\n    var str s = \"12a\"\n    say s.to_int()\nend()",
+        good: "start:\n    var str s = \" 12 \"\n    say s.trim().to_int() + 1\nend()",
+    },
+    Explanation {
+        code: "E0401",
+        zh: "列表下标超出了范围。长度为 n 的列表，下标只能是 0 到 n - 1；可以先用 len() 检查长度。",
+        en: "A list index is out of range. For a list of length n, valid indices are 0 to n - 1; check the length with len() first.",
+        bad: "start:\n    list int xs = [1, 2]\n    say xs[2]\nend()",
+        good: "start:\n    list int xs = [1, 2]\n    say xs[1]\nend()",
+    },
+    Explanation {
+        code: "E0402",
+        zh: "元组下标超出了范围。元组的元素个数在类型中是固定的，如 (int, str) 只有下标 0 和 1。",
+        en: "A tuple index is out of range. The length of a tuple is fixed by its type; (int, str) only has indices 0 and 1.",
+        bad: "start:\n    var (int, str) t = (1, \"a\")\n    var str s = t[2]\nend()",
+        good: "start:\n    var (int, str) t = (1, \"a\")\n    var str s = t[1]\n    say s\nend()",
+    },
+    Explanation {
+        code: "E0403",
+        zh: "从空的双端队列中取元素。pop_front() 和 pop_back() 之前可以先用 len() 检查队列是否为空。",
+        en: "An element is taken from an empty deque. Check with len() before calling pop_front() or pop_back().",
+        bad: "start:\n    deque int q = []\n    var int x = q.pop_front()\nend()",
+        good: "start:\n    deque int q = [1]\n    if q.len() > 0:\n        var int x = q.pop_front()\n        say x\nend()",
+    },
+    Explanation {
+        code: "E0501",
+        zh: "用 m[键] 读取了字典中不存在的键。可以先用 has(键) 检查，或者用 get(键)，键不存在时它返回 none。",
+        en: "m[key] reads a key that is not in the map. Check with has(key) first, or use get(key), which returns none for a missing key.",
+        bad: "start:\n    map str int ages = {\"Ann\": 30}\n    say ages[\"Bob\"]\nend()",
+        good: "start:\n    map str int ages = {\"Ann\": 30}\n    say ages.get(\"Bob\")\nend()",
+    },
+    Explanation {
+        code: "E0502",
+        zh: "用 remove 删除字典中不存在的键。删除之前可以先用 has(键) 检查。",
+        en: "remove is called with a key that is not in the map. Check with has(key) before removing.",
+        bad: "start:\n    map str int ages = {\"Ann\": 30}\n    ages.remove(\"Bob\")\nend()",
+        good: "start:\n    map str int ages = {\"Ann\": 30}\n    if ages.has(\"Bob\"):\n        ages.remove(\"Bob\")\n    say ages\nend()",
+    },
+    Explanation {
+        code: "E0503",
+        zh: "用 remove 删除集合中不存在的元素。删除之前可以先用 has(元素) 检查。",
+        en: "remove is called with an element that is not in the set. Check with has(element) before removing.",
+        bad: "start:\n    set int s = {1, 2}\n    s.remove(3)\nend()",
+        good: "start:\n    set int s = {1, 2}\n    if s.has(3):\n        s.remove(3)\n    say s\nend()",
+    },
+    Explanation {
+        code: "E0601",
+        zh: "除数为 0。/ 和 % 的右边不能是 0；除数来自输入或计算结果时，先用 if 检查它不为 0。",
+        en: "Division by zero. The right-hand side of / and % cannot be 0; when the divisor comes from input or a calculation, check it with if first.",
+        bad: "start:\n    var int d = 0\n    say 10 / d\nend()",
+        good: "start:\n    var int d = 0\n    if d != 0:\n        say 10 / d\nend()",
+    },
+    Explanation {
+        code: "E0701",
+        zh: "无法打开脚本文件或要改写的文件。请检查文件名和路径是否正确，以及是否有读取权限。",
+        en: "The script file, or the file to rewrite, cannot be opened. Check the file name and path and that the file is readable.",
+        bad: "$ hitcode hello.hx",
+        good: "$ hitcode hello.hc",
+    },
+    Explanation {
+        code: "E0702",
+        zh: "读取脚本文件时出错，例如文件不是 UTF-8 编码（一些编辑器默认用 GBK 保存中文）。请把文件另存为 UTF-8 编码。",
+        en: "The script file could not be read, e.g. because it is not UTF-8 encoded (some editors save Chinese text as GBK by default). Save the file as UTF-8.",
+        bad: "$ hitcode gbk.hc",
+        good: "$ iconv -f GBK -t UTF-8 gbk.hc > utf8.hc\n$ hitcode utf8.hc",
+    },
+    Explanation {
+        code: "E0703",
+        zh: "hitcode --fix 改写文件时写入失败，例如文件是只读的。请检查文件的写入权限。",
+        en: "hitcode --fix could not write the file back, e.g. because it is read-only. Check that the file is writable.",
+        bad: "$ chmod a-w hello.hc\n$ hitcode --fix hello.hc",
+        good: "$ chmod u+w hello.hc\n$ hitcode --fix hello.hc",
+    },
+    Explanation {
+        code: "E0704",
+        zh: "向标准输出写入失败，例如输出重定向到的磁盘已满。",
+        en: "Writing to standard output failed, e.g. because output is redirected to a full disk.",
+        bad: "$ hitcode report.hc > /dev/full",
+        good: "$ hitcode report.hc > report.txt",
+    },
+    Explanation {
+        code: "E0705",
+        zh: "input() 读取输入失败，例如输入已经结束（从文件或管道读入时内容不够）。请确认提供了足够的输入行。",
+        en: "input() could not read a line, e.g. because the input has ended (not enough lines when reading from a file or pipe). Make sure enough input lines are provided.",
+        bad: "$ hitcode ask_two_numbers.hc < one_line.txt",
+        good: "$ hitcode ask_two_numbers.hc < two_lines.txt",
+    },
+    Explanation {
+        code: "E0800",
+        zh: "用 throw 抛出的错误没有被 try/catch 捕获，程序结束并以退出码 1 退出。需要在出错时继续运行时，把可能抛出错误的代码放进 try 块并用 catch 处理。",
+        en: "An error raised with throw was not caught by try/catch, so the program ends with exit code 1. Put code that may throw into a try block and handle the error in catch to keep running.",
+        bad: "start:\n    throw \"not ready\"\nend()",
+        good: "start:\n    try:\n        throw \"not ready\"\n    catch err:\n        say err.message\nend()",
+    },
+    Explanation {
+        code: "W0001",
+        zh: "switch 中有两个相同的 case 标签。匹配时总是选中第一个，后面那个分支永远不会执行。",
+        en: "A switch has two identical case labels. The first one always matches, so the later branch never runs.",
+        bad: "start:\n    var int n = 1\n    switch n:\n        case 1:\n            say \"one\"\n        case 1:\n            say \"uno\"\nend()",
+        good: "start:\n    var int n = 1\n    switch n:\n        case 1:\n            say \"one\"\n        case 2:\n            say \"two\"\nend()",
+    },
+    Explanation {
+        code: "W0002",
+        zh: "对枚举值做 switch 时，case 标签不是这个枚举的成员，这个分支永远不会匹配。",
+        en: "In a switch over an enum value, a case label is not a variant of that enum, so the branch can never match.",
+        bad: "enum Color: Red, Green\n\nstart:\n    var Color c = Color.Red\n    switch c:\n        case Red:\n            say \"red\"\n        case Blue:\n            say \"blue\"\n        default:\n            say \"other\"\nend()",
+        good: "enum Color: Red, Green\n\nstart:\n    var Color c = Color.Red\n    switch c:\n        case Red:\n            say \"red\"\n        case Green:\n            say \"green\"\nend()",
+    },
+    Explanation {
+        code: "W0003",
+        zh: "case 中绑定的变量比枚举成员的载荷字段多，多出来的名字不会得到值。",
+        en: "A case binds more names than the enum variant has payload fields; the extra names get no value.",
+        bad: "enum Shape: Empty, Circle(double r)\n\nstart:\n    var Shape s = Shape.Circle(1.0)\n    switch s:\n        case Circle(r, extra):\n            say r\n        default:\n            say \"other\"\nend()",
+        good: "enum Shape: Empty, Circle(double r)\n\nstart:\n    var Shape s = Shape.Circle(1.0)\n    switch s:\n        case Circle(r):\n            say r\n        default:\n            say \"other\"\nend()",
+    },
+    Explanation {
+        code: "W0004",
+        zh: "对枚举值做 switch 时没有覆盖所有成员，也没有 default 分支。值是缺少的成员时不会执行任何分支；请补上缺少的 case，或者加一个 default。",
+        en: "A switch over an enum value does not cover every variant and has no default branch. When the value is a missing variant no branch runs; add the missing cases or a default.",
+        bad: "enum Color: Red, Green\n\nstart:\n    var Color c = Color.Red\n    switch c:\n        case Red:\n            say \"red\"\nend()",
+        good: "enum Color: Red, Green\n\nstart:\n    var Color c = Color.Red\n    switch c:\n        case Red:\n            say \"red\"\n        case Green:\n            say \"green\"\nend()",
+    },
+    Explanation {
+        code: "W0005",
+        zh: "代码中出现了全角符号，通常是中文输入法没有切换回英文。解释器会按对应的半角符号处理；运行 hitcode --fix 文件.hc 可以把文件中的全角符号一次改写成半角。字符串中的全角符号保持不变。",
+        en: "The code contains a full-width punctuation mark, usually because a Chinese input method was left on. The interpreter treats it as the matching ASCII character; hitcode --fix file.hc rewrites all of them at once. Full-width characters inside strings are left alone.",
+        bad: "start:\n    var int x ＝ 1\n    say x\nend()",
+        good: "start:\n    var int x = 1\n    say x\nend()",
+    },
+];
+
+
+// 说明的第一句，列出错误码时使用
+fn explanation_summary(e: &Explanation) -> &'static str {
+    match lang() {
+        Lang::ZhCn => e.zh.split_inclusive('。').next().unwrap_or(e.zh),
+        Lang::EnUs => e.en.split_inclusive(". ").next().unwrap_or(e.en).trim_end(),
+    }
+}
+
+// hitcode explain 错误码：输出详细说明和示例；没有给出错误码时列出所有错误码，返回退出码
+fn explain(code: Option<&str>) -> Result<i32, HcError> {
+    let Some(code) = code else {
+        for e in EXPLANATIONS {
+            write_line(&format!("{}  {}", e.code, explanation_summary(e)))?;
+        }
+        return Ok(0);
+    };
+    let code = code.trim().to_ascii_uppercase();
+    let indent = |example: &str| example.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("    {}", line) })
+        .collect::<Vec<_>>().join("\n");
+    let kind = ErrorKind::from_code(&code);
+    if let Some(e) = EXPLANATIONS.iter().find(|e| e.code == code) {
+        let label = if code.starts_with('W') { "warning" } else { kind.name() };
+        let text = match lang() {
+            Lang::ZhCn => e.zh,
+            Lang::EnUs => e.en,
+        };
+        write_line(&format!("{} ({})\n\n{}", e.code, label, text))?;
+        // 不再使用的错误码没有示例
+        if !e.bad.is_empty() {
+            write_line(&format!("\n{}\n\n{}\n", tr("explain.bad", &[]), indent(e.bad)))?;
+            write_line(&format!("{}\n\n{}", tr("explain.good", &[]), indent(e.good)))?;
+        }
+        return Ok(0);
+    }
+    // 类别的通用错误码（如 E0100）：列出这一段中的具体错误码
+    if kind.code() == code {
+        write_line(&format!("{} ({})\n\n{}\n", code, kind.name(), tr("explain.category", &[&code, &tr(&format!("category.{}", code), &[]), &kind.name()])))?;
+        for e in EXPLANATIONS.iter().filter(|e| e.code[..3] == code[..3]) {
+            write_line(&format!("{}  {}", e.code, explanation_summary(e)))?;
+        }
+        return Ok(0);
+    }
+    eprintln!("{}", tr("ui.unknown_code", &[&code]));
+    Ok(EXIT_USAGE)
+}
+
+// 报告错误之后提示可以用 hitcode explain 查看说明；codes 中重复的错误码只提一次
+fn explain_hint(codes: &[&str]) -> Option<String> {
+    let mut unique: Vec<&str> = Vec::new();
+    for code in codes {
+        if !unique.contains(code) { unique.push(code); }
+    }
+    match unique.as_slice() {
+        [] => None,
+        [code] => Some(tr("ui.explain_one", &[code])),
+        [first, ..] => Some(tr("ui.explain_many", &[&unique.join(", "), first])),
+    }
+}
+
+// 运行时错误：类别加消息，在 try 块中可以被 catch 捕获
+#[derive(Clone, Debug)]
+struct HcError {
+    kind: ErrorKind,
+    // 错误码，也是消息目录中的键；throw 抛出的错误使用类别的错误码
+    code: &'static str,
+    message: String,
+    // 出错位置和调用栈，在错误向外传播时填写
+    trace: Box<Backtrace>,
+    // 报告时加在消息前的语句上下文，如 "变量 x 赋值失败:"；catch 得到的 message 不含它
+    context: Option<String>,
+    // 诊断中需要标出的源码片段，找不到时标出整条语句
+    focus: Option<String>,
+}
+
+impl HcError {
+    fn new(kind: ErrorKind, message: String) -> Self {
+        HcError { code: kind.code(), kind, message, trace: Box::default(), context: None, focus: None }
+    }
+
+    // 内置错误：按错误码从消息目录取出消息，类别由错误码的编号段决定
+    fn of(code: &'static str, args: &[&dyn fmt::Display]) -> Self {
+        HcError { code, ..HcError::new(ErrorKind::from_code(code), tr(code, args)) }
+    }
+
+    // 在消息前加上上下文，类别保持不变
+    fn context(self, prefix: &str) -> Self {
+        HcError { message: format!("{} {}", prefix, self.message), ..self }
+    }
+
+    // 设置报告时的语句上下文；已经有上下文（来自更内层的语句）时保持不变
+    fn within(self, context: String) -> Self {
+        HcError { context: self.context.or(Some(context)), ..self }
+    }
+
+    fn focus(self, text: &str) -> Self {
+        HcError { focus: Some(text.to_string()), ..self }
+    }
+
+    // 错误经过一条语句：记录出错的源码行和当时的调用栈；已经记录过时保持最内层的位置
+    fn at(mut self, line: &SourceLine, frames: &[Frame]) -> Self {
+        if self.trace.source.is_none() {
+            self.trace.source = Some(line.clone());
+        }
+        self.capture(frames, line.number)
+    }
+
+    // 记录出错时的调用栈，line 为最内层函数中执行到的行号（0 表示未知）
+    fn capture(mut self, frames: &[Frame], line: usize) -> Self {
+        if self.trace.frames.is_none() {
+            self.trace.frames = Some(frames.to_vec());
+            self.trace.line = line;
+        }
+        self
+    }
+
+    // 报告时显示的完整消息
+    fn full_message(&self) -> String {
+        match &self.context {
+            Some(context) => format!("{} {}", context, self.message),
+            None => self.message.clone(),
+        }
+    }
+
+    // catch 绑定的错误值 Error(kind: "...", message: "...")；消息中的双引号等由 quote_str 转义
+    fn to_value(&self) -> String {
+        format_struct("Error", &[
+            ("kind".to_string(), format!("\"{}\"", self.kind.name())),
+            ("message".to_string(), quote_str(&self.message)),
+        ])
+    }
+}
+
+// 错误的位置：出错的源码行，以及出错时的调用栈
+#[derive(Clone, Debug, Default)]
+struct Backtrace {
+    source: Option<SourceLine>,
+    frames: Option<Vec<Frame>>,
+    // 最内层函数中执行到的行号，0 表示未知
+    line: usize,
+}
+
+// 调用栈中的一层：函数名、调用所在的行号和参数
+#[derive(Clone, Debug)]
+struct Frame {
+    function: String,
+    call_line: usize,
+    args: Entries,
+}
+
+// 调用栈的文字形式，最近的调用在前，最外层是 start；line 为最内层正在执行的行号
+fn stack_lines(prog: &Program, frames: &[Frame], line: usize) -> Vec<String> {
+    let location = |name: String, line: usize| if line == 0 { name } else { format!("{} ({}:{})", name, prog.file, line) };
+    let mut lines = Vec::new();
+    let mut line = line;
+    for frame in frames.iter().rev() {
+        lines.push(location(format_struct(&frame.function, &frame.args), line));
+        line = frame.call_line;
+    }
+    lines.push(location("start".to_string(), line));
+    lines
+}
+
+// 源码行：文本和行号（从 1 开始），可以直接当作 &str 使用
+#[derive(Clone, Debug)]
+struct SourceLine {
+    text: String,
+    number: usize,
+}
+
+impl Deref for SourceLine {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+// 诊断信息：文件、行、列、级别、错误码和源码片段，按 rustc 的格式输出
+//   error[E0100]: 变量 x 类型不匹配: "abc"
+//     --> demo.hc:3:5
+//      |
+//    3 |     var int x = "abc"
+//      |     ^^^^^^^^^^^^^^^^^
+#[derive(Clone, Debug)]
+struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    file: String,
+    line: usize,
+    // 列号从 1 开始，按字符计
+    column: usize,
+    // 标出的字符数
+    width: usize,
+    snippet: String,
+}
+
+impl Diagnostic {
+    // 在源码行中标出 focus 片段；没有给出或找不到时标出整条语句
+    // 信息、源码和 focus 中的关键字按文件使用的关键字集合显示
+    fn new(severity: Severity, code: &'static str, message: String, file: &str, source: &SourceLine, focus: Option<&str>, keywords: Keywords) -> Self {
+        let source = &SourceLine { text: keywords.render(source), number: source.number };
+        let message = render_message(keywords, &message);
+        let focus = focus.map(|f| keywords.render(f));
+        let focus = focus.as_deref();
+        let stmt = source.trim();
+        let stmt_start = source.len() - source.trim_start().len();
+        let (start, text) = focus.filter(|f| !f.is_empty())
+            .and_then(|f| find_token(&source[stmt_start..], f).map(|idx| (stmt_start + idx, f)))
+            .unwrap_or((stmt_start, stmt));
+        Diagnostic {
+            severity,
+            code,
+            message,
+            file: file.to_string(),
+            line: source.number,
+            column: source[..start].chars().count() + 1,
+            width: text.chars().count().max(1),
+            snippet: source.text.clone(),
+        }
+    }
+}
+
+// 信息中的关键字按文件的关键字集合显示；英文信息保持原样，避免把英文单词当作关键字改写
+fn render_message(keywords: Keywords, message: &str) -> String {
+    match lang() {
+        Lang::ZhCn => keywords.render(message),
+        Lang::EnUs => message.to_string(),
+    }
+}
+
+// 查找片段的位置，片段两侧不能紧挨着标识符字符（避免 x 匹配到 xs 中间）
+fn find_token(s: &str, token: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    s.match_indices(token).map(|(idx, _)| idx).find(|&idx| {
+        let before = s[..idx].chars().next_back();
+        let after = s[idx + token.len()..].chars().next();
+        let joined_before = token.starts_with(is_word) && before.is_some_and(is_word);
+        let joined_after = token.ends_with(is_word) && after.is_some_and(is_word);
+        !joined_before && !joined_after
+    })
+}
+
+// 终端显示宽度：中日韩文字和全角符号占两格
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c as u32 >= 0x2E80 { 2 } else { 1 }).sum()
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let gutter = " ".repeat(self.line.to_string().len());
+        let before: String = self.snippet.chars().take(self.column - 1).collect();
+        let marked: String = self.snippet.chars().skip(self.column - 1).take(self.width).collect();
+        writeln!(f, "{}[{}]: {}", label, self.code, self.message)?;
+        writeln!(f, "{}--> {}:{}:{}", gutter, self.file, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{} | {}{}", gutter, " ".repeat(display_width(&before)), "^".repeat(display_width(&marked).max(1)))
+    }
+}
+
+// 输出运行时错误和调用栈；没有源码位置时只输出消息
+fn report(prog: &Program, err: &HcError) {
+    match &err.trace.source {
+        Some(source) => {
+            let diag = Diagnostic::new(Severity::Error, err.code, err.full_message(), &prog.file, source, err.focus.as_deref(), prog.keywords);
+            eprintln!("{}", diag);
+        }
+        None => eprintln!("error[{}]: {}", err.code, render_message(prog.keywords, &err.full_message())),
+    }
+    let frames = err.trace.frames.as_deref().unwrap_or_default();
+    eprintln!("{}", tr("ui.stack", &[]));
+    for line in stack_lines(prog, frames, err.trace.line) {
+        eprintln!("{}", tr("ui.frame", &[&prog.keywords.render(&line)]));
+    }
+    if let Some(hint) = explain_hint(&[err.code]) {
+        eprintln!("{}", hint);
+    }
+}
+
+// 在读入程序之前失败（如文件无法打开），报告后退出
+fn fail(err: &HcError) -> ! {
+    eprintln!("error[{}]: {}", err.code, err.full_message());
+    process::exit(err.kind.exit_code())
+}
+
+// 向标准输出写一行，写入失败时报 E0704；读取输出的一方已经关闭管道（如 | head）时直接正常结束
+fn write_line(text: &str) -> Result<(), HcError> {
+    match writeln!(io::stdout().lock(), "{}", text) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        result => result.map_err(|e| HcError::of("E0704", &[&e])),
+    }
+}
+
+// 没有提供脚本文件时的退出码
+const EXIT_USAGE: i32 = 64;
+
+// hitcode check 发现错误时的退出码
+const EXIT_CHECK_FAILED: i32 = 1;
+
+// 程序定义：函数体、签名、返回表达式与结构体定义，加载完成后只读
+struct Program {
+    // 源文件名，用于诊断输出
+    file: String,
+    functions: HashMap<String, Vec<SourceLine>>,
+    function_sigs: HashMap<String, FunctionSig>,
+    function_returns: HashMap<String, String>,
+    // 函数结尾 end(...) 所在的行
+    function_ends: HashMap<String, SourceLine>,
+    structs: HashMap<String, Fields>,
+    // 枚举成员表，按定义顺序保存，每个成员可以带载荷字段
+    enums: HashMap<String, Vec<(String, Fields)>>,
+    // 模块级的 var/const 声明，在 start 块之前执行，函数中也可以使用
+    globals: Vec<SourceLine>,
+    // 文件使用的关键字集合，诊断信息按它显示
+    keywords: Keywords,
+    // 紧接在函数定义前面的 ## 文档注释，hitcode doc 显示
+    function_docs: HashMap<String, String>,
+}
+
+// 运行时状态：变量/常量/各类集合及其类型表
+#[derive(Clone, Default)]
+struct Runtime {
+    var_types: HashMap<String, VarType>,
+    const_types: HashMap<String, VarType>,
+    variables: HashMap<String, String>,
+    constants: HashMap<String, String>,
+    lists: HashMap<String, Vec<String>>,
+    // 字典按插入顺序保存键值对，保证输出和遍历顺序确定
+    maps: HashMap<String, Entries>,
+    // 集合同样按插入顺序保存，元素不重复
+    sets: HashMap<String, Vec<String>>,
+    deques: HashMap<String, VecDeque<String>>,
+    // 名字 -> 声明所在行（函数参数为 0），用于发现重复声明
+    decls: HashMap<String, usize>,
+    // 调用栈，函数调用时在局部作用域中压入一层
+    frames: Vec<Frame>,
+    // 模块级声明的名字，函数体对它们的修改会写回调用方
+    globals: Vec<String>,
+    // 正在执行的语句的行号
+    line: usize,
+}
+
+// 命令行选项
+#[derive(Default)]
+struct Options {
+    // hitcode check 文件.hc 只做静态检查，不执行程序
+    check_only: bool,
+    filename: Option<String>,
+    // --tab-width N：Tab 缩进按 N 列计算，此时允许 Tab 和空格混用
+    tab_width: Option<usize>,
+    // --fix：把文件中的全角符号改写成半角，不执行程序
+    fix: bool,
+    // --keywords zh|en：与 --fix 一起使用，把文件改写为中文或英文关键字
+    keywords: Option<Keywords>,
+    // hitcode explain [错误码]：显示错误码的详细说明，不运行程序
+    explain: bool,
+    // hitcode doc 文件.hc：列出函数签名和 ## 文档注释，不运行程序
+    doc: bool,
+    code: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        // 子命令只能写在文件名和其他子命令之前
+        let no_subcommand = options.filename.is_none() && !options.check_only && !options.explain && !options.doc;
+        // --tab-width N 或 --tab-width=N
+        let tab_width = match arg.strip_prefix("--tab-width=") {
+            Some(value) => Some(value.to_string()),
+            None if arg == "--tab-width" => Some(args.next().cloned().unwrap_or_default()),
+            None => None,
+        };
+        if let Some(value) = tab_width {
+            options.tab_width = Some(value.parse().ok().filter(|&n| n > 0)
+                .ok_or_else(|| tr("ui.tab_width", &[&value]))?);
+        } else if arg == "--fix" {
+            options.fix = true;
+        } else if arg == "--keywords" || arg.starts_with("--keywords=") {
+            let value = match arg.strip_prefix("--keywords=") {
+                Some(value) => value.to_string(),
+                None => args.next().cloned().unwrap_or_default(),
+            };
+            options.keywords = Some(Keywords::from_name(&value).ok_or_else(|| tr("ui.keywords", &[&value]))?);
+        } else if arg == "--lang" || arg.starts_with("--lang=") {
+            let value = match arg.strip_prefix("--lang=") {
+                Some(value) => value.to_string(),
+                None => args.next().cloned().unwrap_or_default(),
+            };
+            // 语言已经由 lang_arg 设置，这里只检查取值
+            Lang::from_name(&value).ok_or_else(|| tr("ui.lang", &[&value]))?;
+        } else if arg == "check" && no_subcommand {
+            options.check_only = true;
+        } else if arg == "explain" && no_subcommand {
+            options.explain = true;
+        } else if arg == "doc" && no_subcommand {
+            options.doc = true;
+        } else if arg.starts_with("--") {
+            return Err(tr("ui.unknown_option", &[arg]));
+        } else if options.explain && options.code.is_none() {
+            options.code = Some(arg.clone());
+        } else if options.explain {
+            return Err(tr("ui.extra_arg", &[arg]));
+        } else if options.filename.is_none() {
+            options.filename = Some(arg.clone());
+        } else {
+            return Err(tr("ui.extra_arg", &[arg]));
+        }
+    }
+    if options.keywords.is_some() && !options.fix {
+        return Err(tr("ui.keywords_fix", &[]));
+    }
+    Ok(options)
+}
+
+// --lang zh-CN|en-US：诊断和提示信息的语言，优先于 LANG 环境变量
+// 在解析其余参数之前先找出它，参数错误的提示也使用指定的语言
+fn lang_arg(args: &[String]) -> Option<Lang> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--lang=") {
+            Some(value) => value,
+            None if arg == "--lang" => args.next()?,
+            None => continue,
+        };
+        return Lang::from_name(value);
+    }
+    None
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("请提供一个.hc文件");
+    if let Some(lang) = lang_arg(&args) {
+        LANG.get_or_init(|| lang);
+    }
+    let options = parse_args(&args).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        process::exit(EXIT_USAGE);
+    });
+    if options.explain {
+        process::exit(explain(options.code.as_deref()).unwrap_or_else(|e| fail(&e)));
+    }
+    let check_only = options.check_only;
+    let Some(filename) = &options.filename else {
+        eprintln!("{}", tr("ui.no_file", &[]));
+        process::exit(EXIT_USAGE);
+    };
+    if options.fix {
+        let result = fix_file(filename, options.keywords).and_then(|count| match count {
+            0 => write_line(&tr("ui.fix_none", &[filename])),
+            count => write_line(&tr("ui.fix_done", &[&count, filename])),
+        });
+        if let Err(e) = result { fail(&e) }
         return;
     }
-    let filename = &args[1];
-    let file = File::open(filename).expect("无法打开文件");
-    let reader = BufReader::new(file);
-
-    // 类型枚举
-    #[derive(Clone, Debug, PartialEq)]
-    enum VarType { Int, Double, Str, Bool, Unknown }
-    fn parse_type(s: &str) -> VarType {
-        match s.trim() {
-            "int" => VarType::Int,
-            "double" => VarType::Double,
-            "str" => VarType::Str,
-            "bool" => VarType::Bool,
-            _ => VarType::Unknown,
+    let (program, start_commands, load_errors) = load_program(filename, options.tab_width);
+    if !check_only && !load_errors.is_empty() {
+        for diag in &load_errors {
+            eprintln!("{}", diag);
+        }
+        if let Some(hint) = explain_hint(&load_errors.iter().map(|d| d.code).collect::<Vec<_>>()) {
+            eprintln!("{}", hint);
+        }
+        process::exit(ErrorKind::Syntax.exit_code());
+    }
+    if options.doc {
+        if let Err(e) = print_docs(&program) { fail(&e) }
+        return;
+    }
+    check_switches(&program, &start_commands);
+    if check_only {
+        let mut diagnostics = load_errors;
+        diagnostics.extend(check_program(&program, &start_commands));
+        for diag in &diagnostics {
+            eprintln!("{}", diag);
+        }
+        if !diagnostics.is_empty() {
+            eprintln!("{}", tr("ui.check_failed", &[&diagnostics.len()]));
+            if let Some(hint) = explain_hint(&diagnostics.iter().map(|d| d.code).collect::<Vec<_>>()) {
+                eprintln!("{}", hint);
+            }
+            process::exit(EXIT_CHECK_FAILED);
+        }
+        if let Err(e) = write_line(&tr("ui.check_passed", &[filename])) { fail(&e) }
+        return;
+    }
+    let mut runtime = Runtime::default();
+    let result = eval_block(&program.globals, &program, &mut runtime, 0)
+        .and_then(|_| {
+            runtime.globals = runtime.decls.keys().cloned().collect();
+            eval_block(&start_commands, &program, &mut runtime, 0)
+        });
+    if let Err(e) = result {
+        report(&program, &e);
+        process::exit(e.kind.exit_code());
+    }
+}
+
+// 读入程序：函数、结构体、枚举定义、模块级声明和 start 块
+// 结构错误（顶层的多余语句、函数没有 end、缺少或重复的 start:）一并返回，不在这里退出
+fn load_program(filename: &str, tab_width: Option<usize>) -> (Program, Vec<SourceLine>, Vec<Diagnostic>) {
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(e) => fail(&HcError::of("E0701", &[&filename, &e])),
+    };
+    let lines: Vec<String> = match BufReader::new(file).lines().collect() {
+        Ok(lines) => lines,
+        Err(e) => fail(&HcError::of("E0702", &[&filename, &e])),
+    };
+    // 文件开头注释中的关键字指示，如 # keywords: zh
+    let mut keywords = Keywords::En;
+    let mut pragma_error = None;
+    for (number, line) in lines.iter().enumerate().take_while(|(_, l)| l.trim().is_empty() || l.trim_start().starts_with('#')) {
+        match keyword_pragma(line) {
+            Some(Ok(k)) => keywords = k,
+            Some(Err(value)) => pragma_error = Some((value, SourceLine { text: line.clone(), number: number + 1 })),
+            None => {}
         }
     }
 
-    // 变量/常量类型表
-    let mut var_types: HashMap<String, VarType> = HashMap::new();
-    let mut const_types: HashMap<String, VarType> = HashMap::new();
-    let mut variables: HashMap<String, String> = HashMap::new();
-    let mut constants: HashMap<String, String> = HashMap::new();
-    let mut lists: HashMap<String, Vec<String>> = HashMap::new();
-    let mut functions: HashMap<String, Vec<String>> = HashMap::new();
+    let mut functions: HashMap<String, Vec<SourceLine>> = HashMap::new();
     // 新增：函数签名和返回值表
-    let mut function_sigs: HashMap<String, (Vec<(String, String)>, Option<String>)> = HashMap::new();
+    let mut function_sigs: HashMap<String, FunctionSig> = HashMap::new();
     let mut function_returns: HashMap<String, String> = HashMap::new();
-    let mut start_commands: Vec<String> = Vec::new();
+    let mut function_ends: HashMap<String, SourceLine> = HashMap::new();
+    let mut function_docs: HashMap<String, String> = HashMap::new();
+    // 还没有遇到函数定义的 ## 文档注释行
+    let mut pending_doc: Vec<String> = Vec::new();
+    let mut structs: HashMap<String, Fields> = HashMap::new();
+    let mut enums: HashMap<String, Vec<(String, Fields)>> = HashMap::new();
+    let mut start_commands: Vec<SourceLine> = Vec::new();
+    let mut globals: Vec<SourceLine> = Vec::new();
     let mut current_function: Option<String> = None;
     let mut in_start_section = false;
+    // 当前函数头和 start: 所在的行，用于报告没有结束的函数和重复的 start:
+    let mut function_line: Option<SourceLine> = None;
+    let mut start_line: Option<SourceLine> = None;
+    let mut first_line: Option<SourceLine> = None;
+    // 未指定 --tab-width 时整个文件只能用一种缩进字符：(字符, 第一次出现的行)
+    let mut indent_char: Option<(char, usize)> = None;
+    // 当前函数或 start 块中已打开的缩进层级，以及上一行是否以 : 开启了子块
+    let mut indent_stack: Vec<usize> = Vec::new();
+    let mut opens_block = false;
+    let mut errors = Vec::new();
+    let mut syntax_error = |code: &'static str, args: &[&dyn fmt::Display], source: &SourceLine, focus: Option<&str>| {
+        errors.push(Diagnostic::new(Severity::Error, code, tr(code, args), filename, source, focus, keywords));
+    };
+    if let Some((value, source)) = pragma_error {
+        syntax_error("E0023", &[&value], &source, None);
+    }
 
-    for line in reader.lines() {
-        let line = line.expect("读取行失败");
-        let raw_line = line.clone();
+    let mut lines = lines.into_iter().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let line = if line.trim_start().starts_with('#') { line } else {
+            // 去掉注释，字符串字面量改写成统一的形式；三引号字符串和块注释可以跨行，把后面的行接上直到结束
+            let mut text = line.clone();
+            let lexed = loop {
+                match lex_line(&text) {
+                    Err(("E0025" | "E0028", _)) if let Some((_, next)) = lines.next() => {
+                        text.push('\n');
+                        text.push_str(&next);
+                    }
+                    result => break result,
+                }
+            };
+            let lexed = match lexed {
+                Ok(lexed) => lexed,
+                Err((code, snippet)) => {
+                    syntax_error(code, &[&snippet], &SourceLine { text: line, number: number + 1 }, Some(&snippet));
+                    continue;
+                }
+            };
+            // 中文输入法打出的全角符号按半角处理，并给出警告
+            let (fixed, replaced) = normalize_fullwidth(&lexed);
+            let original = SourceLine { text: lexed, number: number + 1 };
+            for (full, ascii, name) in replaced {
+                let message = tr("W0005", &[&tr(name, &[]), &full, &ascii]);
+                eprintln!("{}", Diagnostic::new(Severity::Warning, "W0005", message, filename, &original, Some(&full.to_string()), keywords));
+            }
+            // 中文关键字换成英文关键字，之后按英文关键字解析
+            match keywords {
+                Keywords::Zh => translate_keywords(&fixed, Keywords::En),
+                Keywords::En => fixed,
+            }
+        };
+        let mut raw_line = SourceLine { text: line.clone(), number: number + 1 };
         let line = line.trim_end();
-        // 跳过空行和注释
-        if line.is_empty() || line.starts_with('#') { continue; }
+        // 跳过空行和注释（包括缩进的注释），顶格的 ## 文档注释留给后面的函数
+        if line.is_empty() || line.trim_start().starts_with('#') {
+            if let Some(doc) = line.strip_prefix("##") {
+                pending_doc.push(doc.strip_prefix(' ').unwrap_or(doc).to_string());
+            }
+            continue;
+        }
+        let doc = std::mem::take(&mut pending_doc);
+        first_line.get_or_insert_with(|| raw_line.clone());
+        // 缩进统一展开成空格，之后按列数比较
+        let leading: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+        let mut mixed_indent = None;
+        if tab_width.is_none() && !line.trim_start().starts_with('#') {
+            let (has_tab, has_space) = (leading.contains('\t'), leading.chars().any(|c| c != '\t'));
+            if has_tab && has_space {
+                mixed_indent = Some(("E0017", 0));
+            } else if has_tab || has_space {
+                let c = if has_tab { '\t' } else { ' ' };
+                match indent_char {
+                    Some((first, first_number)) if first != c => {
+                        mixed_indent = Some((if has_tab { "E0018" } else { "E0019" }, first_number));
+                    }
+                    None => indent_char = Some((c, raw_line.number)),
+                    _ => {}
+                }
+            }
+        }
+        raw_line.text = expand_indent(&raw_line, tab_width.unwrap_or(DEFAULT_TAB_WIDTH));
+        if let Some((code, first_number)) = mixed_indent {
+            syntax_error(code, &[&first_number], &raw_line, None);
+        }
+        let line = raw_line.text.trim_end().to_string();
+        let line = line.as_str();
+        // 顶层的定义或 start: 出现时，前面的函数或 start 块必须已经结束
+        let is_definition = ["function ", "struct ", "enum "].iter().any(|k| line.starts_with(k));
+        if let Some(func_name) = &current_function && (is_definition || line == "start:") {
+            if let Some(head) = &function_line {
+                syntax_error("E0012", &[func_name], head, Some(func_name));
+            }
+            current_function = None;
+        }
+        if in_start_section && is_definition {
+            if let Some(head) = &start_line {
+                syntax_error("E0013", &[], head, None);
+            }
+            in_start_section = false;
+        }
         // 支持函数定义带参数和返回值 function 名(类型 参数, ...)->return::类型
-        if line.starts_with("function ") {
-            let def = line[9..].trim_end_matches(':').trim();
+        if let Some(def) = line.strip_prefix("function ") {
+            let def = def.trim_end_matches(':').trim();
             let (name_and_params, ret_type) = if let Some((left, right)) = def.split_once("->return::") {
                 (left.trim(), Some(right.trim()))
             } else {
                 (def, None)
             };
             let (fname, params) = if let Some(lparen) = name_and_params.find('(') {
-                let rparen = name_and_params.find(')').unwrap_or(name_and_params.len());
+                let rparen = name_and_params.rfind(')').unwrap_or(name_and_params.len());
                 let fname = name_and_params[..lparen].trim();
                 let params_str = &name_and_params[lparen+1..rparen];
                 (fname, params_str)
             } else {
                 (name_and_params, "")
             };
-            let param_list: Vec<(String, String)> = params.split(',')
-                .filter(|s| !s.trim().is_empty())
-                .map(|s| {
-                    let mut p = s.trim().split_whitespace();
-                    (p.next().unwrap_or("").to_string(), p.next().unwrap_or("").to_string())
-                }).collect();
             let ret_type = ret_type.map(|s| s.to_string());
-            function_sigs.insert(fname.to_string(), (param_list.clone(), ret_type.clone()));
+            function_sigs.insert(fname.to_string(), (parse_params(params), ret_type));
+            if !doc.is_empty() {
+                function_docs.insert(fname.to_string(), doc.join("\n"));
+            }
             current_function = Some(fname.to_string());
+            function_line = Some(raw_line.clone());
+            indent_stack.clear();
             functions.insert(fname.to_string(), Vec::new());
             continue;
         }
+        // 结构体定义 struct 名: 类型 字段, ...
+        if let Some(def) = line.strip_prefix("struct ") {
+            let (name, fields) = def.split_once(':').unwrap_or((def, ""));
+            structs.insert(name.trim().to_string(), parse_params(fields));
+            continue;
+        }
+        // 枚举定义 enum 名: 成员, 成员(类型 字段, ...), ...
+        if let Some(def) = line.strip_prefix("enum ") {
+            let (name, variants) = def.split_once(':').unwrap_or((def, ""));
+            let variants = split_top_level(variants, ',').into_iter()
+                .filter(|v| !v.is_empty())
+                .map(|v| {
+                    let (vname, fields) = split_call(&v);
+                    (vname.to_string(), parse_params(fields))
+                })
+                .collect();
+            enums.insert(name.trim().to_string(), variants);
+            continue;
+        }
         // end(返回值) 或 end() 处理
         if let Some(ref func_name) = current_function {
-            if line.trim_start().starts_with("end(") {
-                let ret_val = line.trim_start()[4..].trim_end_matches(')').trim();
-                function_returns.insert(func_name.clone(), ret_val.to_string());
-                current_function = None;
-            } else if line.trim_start() == "end" || line.trim_start() == "end()" {
+            let trimmed = line.trim_start();
+            if trimmed == "end" || trimmed.starts_with("end(") {
+                function_ends.insert(func_name.clone(), raw_line.clone());
+            }
+            if trimmed == "end" || trimmed == "end()" {
                 function_returns.insert(func_name.clone(), String::new());
                 current_function = None;
+            } else if let Some(ret) = trimmed.strip_prefix("end(") {
+                let ret_val = ret.strip_suffix(')').unwrap_or(ret).trim();
+                function_returns.insert(func_name.clone(), ret_val.to_string());
+                current_function = None;
             } else {
+                if let Some(code) = check_indent(&mut indent_stack, &mut opens_block, line) {
+                    syntax_error(code, &[], &raw_line, None);
+                }
                 functions.get_mut(func_name).unwrap().push(raw_line);
             }
             continue;
         }
         // 进入start区块
         if line == "start:" {
+            if let Some(first) = &start_line {
+                syntax_error("E0014", &[&first.number], &raw_line, None);
+            }
+            start_line = Some(raw_line.clone());
             in_start_section = true;
+            indent_stack.clear();
             continue;
         }
-        // 收集start区块命令
+        // 收集start区块命令，end 或 end() 结束 start 块
         if in_start_section {
-            if line == "end" {
+            if line == "end" || line == "end()" {
                 in_start_section = false;
             } else {
+                if let Some(code) = check_indent(&mut indent_stack, &mut opens_block, line) {
+                    syntax_error(code, &[], &raw_line, None);
+                }
                 start_commands.push(raw_line);
             }
             continue;
         }
+        // 模块级声明：顶层的 var、const 以及 list/map/set/deque
+        let is_declaration = ["var ", "const "].iter().any(|k| line.starts_with(k))
+            || COLLECTION_KINDS.iter().any(|(kind, _, _)| line.strip_prefix(kind).is_some_and(|r| r.starts_with(' ')));
+        if is_declaration {
+            globals.push(raw_line);
+            continue;
+        }
+        syntax_error("E0015", &[&line.trim()], &raw_line, None);
+    }
+    if let (Some(func_name), Some(head)) = (&current_function, &function_line) {
+        syntax_error("E0012", &[func_name], head, Some(func_name));
+    }
+    if in_start_section && let Some(head) = &start_line {
+        syntax_error("E0013", &[], head, None);
+    }
+    if start_line.is_none() {
+        let source = first_line.unwrap_or(SourceLine { text: String::new(), number: 1 });
+        syntax_error("E0016", &[], &source, None);
     }
 
-    // 递归执行代码块，基于缩进
-    fn eval_block(
-        block: &[String],
-        functions: &HashMap<String, Vec<String>>,
-        variables: &mut HashMap<String, String>,
-        constants: &mut HashMap<String, String>,
-        lists: &mut HashMap<String, Vec<String>>,
-        var_types: &mut HashMap<String, VarType>,
-        const_types: &mut HashMap<String, VarType>,
-        function_sigs: &HashMap<String, (Vec<(String, String)>, Option<String>)>,
-        function_returns: &HashMap<String, String>,
-        parent_indent: usize,
-    ) {
-        use std::collections::HashSet;
-        let mut called_once: HashSet<String> = HashSet::new();
-        let mut i = 0;
-        while i < block.len() {
-            let line = &block[i];
-            let cmd = line.trim();
-            if cmd.is_empty() { i += 1; continue; }
-            let indent = line.chars().take_while(|c| c.is_whitespace()).count();
-            // 块外，直接返回
-            if indent < parent_indent { return; }
-            let mut jumped = false;
-            // 变量声明
-            if cmd.starts_with("var ") {
-                let rest = &cmd[4..];
-                if let Some((type_and_name, value)) = rest.split_once('=') {
-                    let mut parts = type_and_name.trim().split_whitespace();
-                    let typ = parts.next().unwrap_or("");
-                    let name = parts.next().unwrap_or("");
-                    let vtype = parse_type(typ);
-                    if vtype == VarType::Unknown || name.is_empty() {
-                        eprintln!("变量声明语法错误: {}", cmd);
-                        i += 1; continue;
-                    }
-                    let mut val = value.trim().to_string();
-                    // 支持 input("xxx") 或 input("xxx").to_str()
-                    if vtype == VarType::Str && (val.starts_with("input(") && val.ends_with(")") || val.starts_with("input(") && val.ends_with(").to_str()")) {
-                        let prompt = if let Some(start) = val.find('"') {
-                            let end = val.rfind('"').unwrap_or(val.len()-1);
-                            &val[start+1..end]
-                        } else {
-                            ""
-                        };
-                        use std::io::{self, Write};
-                        print!("{}", prompt);
-                        io::stdout().flush().unwrap();
-                        let mut input = String::new();
-                        io::stdin().read_line(&mut input).unwrap();
-                        let input = input.trim_end_matches(['\r','\n']);
-                        // 存储时加双引号
-                        let input_val = format!("\"{}\"", input);
-                        variables.insert(name.to_string(), input_val);
-                        var_types.insert(name.to_string(), vtype.clone());
-                        i += 1;
-                        continue;
-                    }
-                    // 类型检查
-                    let ok = match vtype {
-                        VarType::Int => val.parse::<i64>().is_ok(),
-                        VarType::Double => val.parse::<f64>().is_ok(),
-                        VarType::Str => val.starts_with('"') && val.ends_with('"'),
-                        VarType::Bool => val == "true" || val == "false",
-                        _ => false,
-                    };
-                    if !ok {
-                        eprintln!("变量 {} 类型不匹配: {}", name, val);
-                    } else {
-                        variables.insert(name.to_string(), val.to_string());
-                    }
-                } else {
-                    eprintln!("变量声明语法错误: {}", cmd);
-                }
-            // 常量声明
-            } else if cmd.starts_with("const ") {
-                let rest = &cmd[6..];
-                if let Some((type_and_name, value)) = rest.split_once('=') {
-                    let mut parts = type_and_name.trim().split_whitespace();
-                    let typ = parts.next().unwrap_or("");
-                    let name = parts.next().unwrap_or("");
-                    let vtype = parse_type(typ);
-                    if vtype == VarType::Unknown || name.is_empty() {
-                        eprintln!("常量声明语法错误: {}", cmd);
-                        i += 1; continue;
-                    }
-                    const_types.insert(name.to_string(), vtype.clone());
-                    let val = value.trim();
-                    let ok = match vtype {
-                        VarType::Int => val.parse::<i64>().is_ok(),
-                        VarType::Double => val.parse::<f64>().is_ok(),
-                        VarType::Str => val.starts_with('"') && val.ends_with('"'),
-                        VarType::Bool => val == "true" || val == "false",
-                        _ => false,
-                    };
-                    if !ok {
-                        eprintln!("常量 {} 类型不匹配: {}", name, val);
-                    } else {
-                        constants.insert(name.to_string(), val.to_string());
-                    }
-                } else {
-                    eprintln!("常量声明语法错误: {}", cmd);
-                }
-            // 支持 list 声明带类型，如 list int a = [1, 2, 3]
-            } else if cmd.starts_with("list ") {
-                let rest = &cmd[5..];
-                let (type_and_name, value) = if let Some((type_and_name, value)) = rest.split_once('=') {
-                    (type_and_name.trim(), value.trim())
-                } else {
-                    (rest.trim(), "")
-                };
-                let mut parts = type_and_name.split_whitespace();
-                let typ = parts.next().unwrap_or("");
-                let name = parts.next().unwrap_or(typ); // 兼容无类型写法
-                let name = name.trim();
-                if value.starts_with('[') && value.ends_with(']') {
-                    let inner = &value[1..value.len()-1];
-                    let items: Vec<String> = inner.split(',').map(|s| s.trim().to_string()).collect();
-                    lists.insert(name.to_string(), items);
-                }
-            } else if cmd.starts_with("say ") {
-                let quote_str = &cmd[4..];
-                if let Some(content) = quote_str.trim().strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
-                    println!("{}", content);
-                } else {
-                    let var_name = quote_str.trim();
-                    // 禁止 say 后直接写函数调用或未加引号的字符串
-                    if var_name.contains('(') || var_name.contains(')') || var_name.contains('"') {
-                        eprintln!("say 语法错误: 只能 say 变量或 say \"字符串\"");
-                    } else if let Some(val) = variables.get(var_name) {
-                        // 如果变量是字符串类型，去除引号输出
-                        if val.starts_with('"') && val.ends_with('"') && val.len() >= 2 {
-                            println!("{}", &val[1..val.len()-1]);
-                        } else {
-                            println!("{}", val);
-                        }
-                    } else if let Some(val) = constants.get(var_name) {
-                        if val.starts_with('"') && val.ends_with('"') && val.len() >= 2 {
-                            println!("{}", &val[1..val.len()-1]);
-                        } else {
-                            println!("{}", val);
-                        }
-                    } else {
-                        eprintln!("say 语法错误: {} 不是已定义变量或字符串", var_name);
-                    }
-                }
-            } else if (cmd.contains("+=") || cmd.contains("-=") || cmd.contains("*=") || cmd.contains("/=") || cmd.contains("%=")) && !cmd.starts_with("const ") && !cmd.starts_with("list ") && !cmd.starts_with("call ") {
-                let op = if cmd.contains("+=") { "+=" } else if cmd.contains("-=") { "-=" } else if cmd.contains("*=") { "*=" } else if cmd.contains("/=") { "/=" } else { "%=" };
-                if let Some((name, value)) = cmd.split_once(op) {
-                    let name = name.trim();
-                    let value = value.trim();
-                    if let Some(old_val) = variables.get(name) {
-                        let left = old_val.parse::<f64>().unwrap_or(0.0);
-                        let right = eval_math_expr(value, &variables, &constants);
-                        let result = match op {
-                            "+=" => left + right,
-                            "-=" => left - right,
-                            "*=" => left * right,
-                            "/=" => left / right,
-                            "%=" => left % right,
-                            _ => left,
-                        };
-                        variables.insert(name.to_string(), result.to_string());
-                    } else {
-                        eprintln!("变量 {} 未定义，不能直接赋值（请用 var {} = ...）", name, name);
-                    }
-                }
-            } else if cmd.contains('=') && !cmd.starts_with("const ") && !cmd.starts_with("list ") && !cmd.starts_with("call ") && !cmd.starts_with("if ") && !cmd.starts_with("while ") && !cmd.starts_with("for ") && !cmd.starts_with("do ") && !cmd.starts_with("switch ") {
-                if let Some((name, value)) = cmd.split_once('=') {
-                    let name = name.trim();
-                    let value = value.trim();
-                    if variables.contains_key(name) {
-                        let result = eval_math_expr(value, &variables, &constants);
-                        variables.insert(name.to_string(), result.to_string());
-                    } else {
-                        eprintln!("变量 {} 未定义，不能直接赋值（请用 var {} = ...）", name, name);
-                    }
-                }
-            } else if cmd.starts_with("call ") {
-                let call_expr = &cmd[5..].trim();
-                let (fname, args): (&str, &str) = if let Some(lparen) = call_expr.find('(') {
-                    let rparen = call_expr.find(')').unwrap_or(call_expr.len());
-                    let fname = &call_expr[..lparen].trim();
-                    let args_str = &call_expr[lparen+1..rparen];
-                    (fname, args_str)
-                } else {
-                    (call_expr, "")
-                };
-                if parent_indent > 0 {
-                    // 静默跳过嵌套块内的 call，不输出任何提示
-                    i += 1;
-                    continue;
-                }
-                // 主块call只执行一次
-                if !called_once.insert(fname.to_string()) {
-                    i += 1;
-                    continue;
-                }
-                let arg_vals: Vec<String> = args.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
-                let mut local_vars = variables.clone();
-                if let Some((params, _ret_type)) = function_sigs.get(fname) {
-                    for ((ptype, pname), val) in params.iter().zip(arg_vals.iter()) {
-                        local_vars.insert(pname.clone(), val.clone());
-                        var_types.insert(pname.clone(), parse_type(ptype));
-                    }
-                }
-                if let Some(statements) = functions.get(fname) {
-                    let mut local_function_returns = function_returns.clone();
-                    eval_block(statements, functions, &mut local_vars, constants, lists, var_types, const_types, function_sigs, &mut local_function_returns, 0);
-                } else {
-                    eprintln!("函数 {} 未定义", fname);
-                }
-                jumped = true;
-            } else if cmd.starts_with("if ") && cmd.ends_with(":") {
-                let cond = cmd[3..cmd.len()-1].trim();
-                let mut inner_block = Vec::new();
-                let mut j = i + 1;
-                let this_indent = indent;
-                while j < block.len() {
-                    let l = &block[j];
-                    let l_indent = l.chars().take_while(|c| c.is_whitespace()).count();
-                    if l_indent <= this_indent { break; }
-                    inner_block.push(block[j].clone());
-                    j += 1;
-                }
-                let cond_result = eval_condition(cond, variables, constants);
-                if cond_result {
-                    eval_block(&inner_block, functions, variables, constants, lists, var_types, const_types, function_sigs, function_returns, this_indent+1);
-                }
-                i = j;
-                jumped = true;
-            } else if cmd.starts_with("while ") && cmd.ends_with(":") {
-                let cond = cmd[6..cmd.len()-1].trim();
-                let mut inner_block = Vec::new();
-                let mut j = i + 1;
-                let this_indent = indent;
-                while j < block.len() {
-                    let l = &block[j];
-                    let l_indent = l.chars().take_while(|c| c.is_whitespace()).count();
-                    if l_indent <= this_indent { break; }
-                    inner_block.push(block[j].clone());
-                    j += 1;
-                }
-                while eval_condition(cond, variables, constants) {
-                    eval_block(&inner_block, functions, variables, constants, lists, var_types, const_types, function_sigs, function_returns, this_indent+1);
-                }
-                i = j;
-                jumped = true;
-            } else if cmd.starts_with("for ") && cmd.ends_with(":") {
-                let cond = cmd[4..cmd.len()-1].trim();
-                if let Some((var, rest)) = cond.split_once(" in ") {
-                    let var = var.trim();
-                    let list_name = rest.trim();
-                    let items_opt = lists.get(list_name).cloned();
-                    if let Some(items) = items_opt {
-                        let mut inner_block = Vec::new();
-                        let mut j = i + 1;
-                        let this_indent = indent;
-                        while j < block.len() {
-                            let l = &block[j];
-                            let l_indent = l.chars().take_while(|c| c.is_whitespace()).count();
-                            if l_indent <= this_indent { break; }
-                            inner_block.push(block[j].clone());
-                            j += 1;
-                        }
-                        for item in items {
-                            variables.insert(var.to_string(), item.clone());
-                            eval_block(&inner_block, functions, variables, constants, lists, var_types, const_types, function_sigs, function_returns, this_indent+1);
-                        }
-                        i = j;
-                        jumped = true;
-                    }
-                }
-            } else if cmd.starts_with("do ") && cmd.ends_with(":") {
-                let cond = cmd[3..cmd.len()-1].trim();
-                let mut inner_block = Vec::new();
-                let mut j = i + 1;
-                let this_indent = indent;
-                while j < block.len() {
-                    let l = &block[j];
-                    let l_indent = l.chars().take_while(|c| c.is_whitespace()).count();
-                    if l_indent <= this_indent { break; }
-                    inner_block.push(block[j].clone());
-                    j += 1;
-                }
-                loop {
-                    eval_block(&inner_block, functions, variables, constants, lists, var_types, const_types, function_sigs, function_returns, this_indent+1);
-                    if !eval_condition(cond, variables, constants) { break; }
-                }
-                i = j;
-                jumped = true;
-            } else if cmd.starts_with("switch ") && cmd.ends_with(":") {
-                let var = cmd[7..cmd.len()-1].trim();
-                let mut j = i + 1;
-                let this_indent = indent;
-                let mut cases: Vec<(String, Vec<String>)> = Vec::new();
-                let mut default_block = Vec::new();
-                let mut current_case: Option<String> = None;
-                let mut current_block: Vec<String> = Vec::new();
-                while j < block.len() {
-                    let l = &block[j];
-                    let ltrim = l.trim();
-                    let l_indent = l.chars().take_while(|c| c.is_whitespace()).count();
-                    if l_indent <= this_indent { break; }
-                    if ltrim.starts_with("case ") && ltrim.ends_with(":") {
-                        if let Some(case_val) = current_case.take() {
-                            cases.push((case_val, current_block.clone()));
-                            current_block.clear();
-                        }
-                        current_case = Some(ltrim[5..ltrim.len()-1].trim().to_string());
-                    } else if ltrim == "default:" {
-                        if let Some(case_val) = current_case.take() {
-                            cases.push((case_val, current_block.clone()));
-                            current_block.clear();
-                        }
-                        current_case = None;
-                    } else {
-                        current_block.push(l.clone());
-                    }
-                    j += 1;
-                }
-                if let Some(case_val) = current_case.take() {
-                    cases.push((case_val, current_block.clone()));
-                } else if !current_block.is_empty() {
-                    default_block = current_block;
-                }
-                let var_val = variables.get(var).or_else(|| constants.get(var)).cloned();
-                let mut matched = false;
-                if let Some(val) = var_val {
-                    for (case_val, block2) in &cases {
-                        if val == *case_val {
-                            eval_block(block2, functions, variables, constants, lists, var_types, const_types, function_sigs, function_returns, this_indent+1);
-                            matched = true;
-                            break;
-                        }
-                    }
-                }
-                if !matched && !default_block.is_empty() {
-                    eval_block(&default_block, functions, variables, constants, lists, var_types, const_types, function_sigs, function_returns, this_indent+1);
-                }
-                i = j;
-                jumped = true;
-            }
-            // 变量声明类型检查增加 call 返回值类型支持
-            if cmd.starts_with("var ") && cmd.contains("= call ") {
-                let rest = &cmd[4..];
-                if let Some((type_and_name, value)) = rest.split_once('=') {
-                    let mut parts = type_and_name.trim().split_whitespace();
-                    let typ = parts.next().unwrap_or("");
-                    let name = parts.next().unwrap_or("");
-                    let vtype = parse_type(typ);
-                    if vtype == VarType::Unknown || name.is_empty() {
-                        eprintln!("变量声明语法错误: {}", cmd);
-                        i += 1; continue;
-                    }
-                    let value = value.trim();
-                    if value.starts_with("call ") {
-                        let call_expr = &value[5..].trim();
-                        let (fname, args): (&str, &str) = if let Some(lparen) = call_expr.find('(') {
-                            let rparen = call_expr.find(')').unwrap_or(call_expr.len());
-                            let fname = &call_expr[..lparen].trim();
-                            let args_str = &call_expr[lparen+1..rparen];
-                            (fname, args_str)
-                        } else {
-                            (call_expr, "")
-                        };
-                        let arg_vals: Vec<String> = args.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
-                        let mut local_vars = variables.clone();
-                        if let Some((params, _ret_type)) = function_sigs.get(fname) {
-                            for ((ptype, pname), val) in params.iter().zip(arg_vals.iter()) {
-                                local_vars.insert(pname.clone(), val.clone());
-                                var_types.insert(pname.clone(), parse_type(ptype));
-                            }
-                        }
-                        if let Some(statements) = functions.get(fname) {
-                            let mut local_function_returns = function_returns.clone();
-                            eval_block(statements, functions, &mut local_vars, constants, lists, var_types, const_types, function_sigs, &mut local_function_returns, 0);
-                            if let Some(ret) = local_function_returns.get(fname) {
-                                // 类型检查
-                                let mut ret_val = ret.clone();
-                                let ok = match vtype {
-                                    VarType::Int => ret_val.parse::<i64>().is_ok(),
-                                    VarType::Double => ret_val.parse::<f64>().is_ok(),
-                                    VarType::Str => {
-                                        if !ret_val.starts_with('"') || !ret_val.ends_with('"') {
-                                            ret_val = format!("\"{}\"", ret_val);
-                                        }
-                                        ret_val.starts_with('"') && ret_val.ends_with('"')
-                                    },
-                                    VarType::Bool => ret_val == "true" || ret_val == "false",
-                                    _ => false,
-                                };
-                                if !ok {
-                                    eprintln!("变量 {} 类型不匹配: {}", name, ret);
-                                } else {
-                                    variables.insert(name.to_string(), ret_val.clone());
-                                    var_types.insert(name.to_string(), vtype.clone());
-                                }
-                            }
-                        } else {
-                            eprintln!("函数 {} 未定义", fname);
-                        }
-                    } else {
-                        eprintln!("变量声明语法错误: {}", cmd);
-                    }
-                } else {
-                    eprintln!("变量声明语法错误: {}", cmd);
-                }
+    // 内置的错误结构体，catch 绑定的值和 throw Error(...) 都使用它
+    structs.entry("Error".to_string()).or_insert_with(|| parse_params("str kind, str message"));
+    let program = Program { file: filename.to_string(), functions, function_sigs, function_returns, function_ends, structs, enums, globals, keywords, function_docs };
+    (program, start_commands, errors)
+}
+
+// 中文输入法常见的全角符号：(全角, 对应的半角, 名称在消息目录中的键)
+const FULLWIDTH_PUNCTUATION: [(char, char, &str); 21] = [
+    ('：', ':', "punct.colon"), ('，', ',', "punct.comma"), ('（', '(', "punct.lparen"), ('）', ')', "punct.rparen"),
+    ('“', '"', "punct.lquote"), ('”', '"', "punct.rquote"), ('＝', '=', "punct.equals"),
+    ('［', '[', "punct.lbracket"), ('］', ']', "punct.rbracket"), ('｛', '{', "punct.lbrace"), ('｝', '}', "punct.rbrace"),
+    ('＋', '+', "punct.plus"), ('－', '-', "punct.minus"), ('＊', '*', "punct.star"), ('／', '/', "punct.slash"), ('％', '%', "punct.percent"),
+    ('＜', '<', "punct.less"), ('＞', '>', "punct.greater"), ('！', '!', "punct.bang"), ('．', '.', "punct.period"), ('　', ' ', "punct.space"),
+];
+
+// 词法处理一行：去掉字符串之外的 # 和 // 行尾注释，块注释 /* ... */ 换成一个空格；
+// 字符串字面量改写成统一的形式，内容中只保留 \\ \" \n \t 四种转义，
+// \u{...}、原始字符串 r"..." 和三引号字符串 """...""" 都改写成普通字符串。
+// 以“开头的字符串保留全角引号，之后由 normalize_fullwidth 处理并给出警告。
+// 出错时返回错误码和出错的片段；E0025 和 E0028 表示字符串或块注释要接着下一行
+fn lex_line(line: &str) -> Result<String, (&'static str, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let triple_at = |i: usize| chars[i..].starts_with(&['"', '"', '"']);
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '#' || chars[i..].starts_with(&['/', '/']) { break; }
+        if chars[i..].starts_with(&['/', '*']) {
+            let end = (i+2..chars.len()).find(|&j| chars[j..].starts_with(&['*', '/']));
+            let Some(end) = end else { return Err(("E0028", "/*".to_string())) };
+            out.push(' ');
+            i = end + 2;
+            continue;
+        }
+        let raw = chars[i] == 'r' && (i == 0 || !is_word_char(chars[i-1])) && matches!(chars.get(i+1), Some('"' | '“'));
+        let start = i;
+        if raw { i += 1; }
+        let open = chars[i];
+        if open != '"' && open != '“' {
+            out.push(open);
+            i += 1;
+            continue;
+        }
+        let triple = !raw && open == '"' && triple_at(i);
+        i += if triple { 3 } else { 1 };
+        let unterminated = |end: usize| if triple {
+            ("E0025", "\"\"\"".to_string())
+        } else {
+            ("E0024", chars[start..end].iter().collect())
+        };
+        let mut content = String::new();
+        let close = loop {
+            let Some(&c) = chars.get(i) else { return Err(unterminated(i)) };
+            if triple && triple_at(i) {
+                i += 3;
+                break '"';
+            }
+            if !triple && (c == '"' || (open == '“' && c == '”')) {
+                i += 1;
+                break c;
+            }
+            if c != '\\' {
+                content.push(c);
                 i += 1;
                 continue;
             }
-            // 普通变量声明（排除 call 情况）
-            if cmd.starts_with("var ") && !cmd.contains("= call ") {
-                let rest = &cmd[4..];
-                if let Some((type_and_name, value)) = rest.split_once('=') {
-                    let mut parts = type_and_name.trim().split_whitespace();
-                    let typ = parts.next().unwrap_or("");
-                    let name = parts.next().unwrap_or("");
-                    let vtype = parse_type(typ);
-                    if vtype == VarType::Unknown || name.is_empty() {
-                        eprintln!("变量声明语法错误: {}", cmd);
-                        i += 1; continue;
-                    }
-                    let mut val = value.trim().to_string();
-                    // 支持 input("xxx") 或 input("xxx").to_str()
-                    if vtype == VarType::Str && (val.starts_with("input(") && val.ends_with(")") || val.starts_with("input(") && val.ends_with(").to_str()")) {
-                        let prompt = if let Some(start) = val.find('"') {
-                            let end = val.rfind('"').unwrap_or(val.len()-1);
-                            &val[start+1..end]
-                        } else {
-                            ""
-                        };
-                        use std::io::{self, Write};
-                        print!("{}", prompt);
-                        io::stdout().flush().unwrap();
-                        let mut input = String::new();
-                        io::stdin().read_line(&mut input).unwrap();
-                        let input = input.trim_end_matches(['\r','\n']);
-                        // 存储时加双引号
-                        let input_val = format!("\"{}\"", input);
-                        variables.insert(name.to_string(), input_val);
-                        var_types.insert(name.to_string(), vtype.clone());
-                        i += 1;
-                        continue;
-                    }
-                    // 类型检查
-                    let ok = match vtype {
-                        VarType::Int => val.parse::<i64>().is_ok(),
-                        VarType::Double => val.parse::<f64>().is_ok(),
-                        VarType::Str => val.starts_with('"') && val.ends_with('"'),
-                        VarType::Bool => val == "true" || val == "false",
-                        _ => false,
-                    };
-                    if !ok {
-                        eprintln!("变量 {} 类型不匹配: {}", name, val);
-                    } else {
-                        variables.insert(name.to_string(), val.to_string());
+            // 原始字符串不处理转义，\" 也只是原样保留、不结束字符串
+            let Some(&next) = chars.get(i+1) else { return Err(unterminated(i+1)) };
+            if raw {
+                content.push(c);
+                if next == '"' || next == '\\' { content.push(next); i += 1; }
+                i += 1;
+                continue;
+            }
+            let (value, len) = match next {
+                'n' => ('\n', 2),
+                't' => ('\t', 2),
+                '"' => ('"', 2),
+                '\\' => ('\\', 2),
+                'u' => {
+                    // \u{XXXX}：1 到 6 位十六进制数，必须是有效的 Unicode 字符
+                    let end = chars[i..].iter().take(10).position(|&c| c == '}').unwrap_or(1);
+                    let escape: String = chars[i..=i+end].iter().collect();
+                    let hex = escape.strip_prefix("\\u{").and_then(|e| e.strip_suffix('}')).unwrap_or("");
+                    let value = Some(hex).filter(|h| !h.is_empty() && h.chars().all(|c| c.is_ascii_hexdigit()))
+                        .and_then(|h| u32::from_str_radix(h, 16).ok())
+                        .and_then(char::from_u32);
+                    match value {
+                        Some(value) => (value, end + 1),
+                        None => return Err(("E0027", escape)),
                     }
+                }
+                other => return Err(("E0026", format!("\\{}", other))),
+            };
+            content.push(value);
+            i += len;
+        };
+        let quoted = quote_str(&content);
+        out.push(open);
+        out.push_str(&quoted[1..quoted.len()-1]);
+        out.push(close);
+    }
+    Ok(out)
+}
+
+// 把字符串字面量之外的全角符号换成半角，返回新行和替换过的符号（每种只记一次）
+// 中文引号“”本身当作字符串的引号，字符串内部的内容保持不变
+fn normalize_fullwidth(line: &str) -> (String, Vec<(char, char, &'static str)>) {
+    let mut fixed = String::with_capacity(line.len());
+    let mut replaced: Vec<(char, char, &'static str)> = Vec::new();
+    let mut record = |entry: (char, char, &'static str)| if !replaced.iter().any(|r| r.0 == entry.0) { replaced.push(entry) };
+    // 在字符串内时记录开头的引号：以“开头的字符串也可以用”结束
+    let mut open_quote: Option<char> = None;
+    for c in line.chars() {
+        match open_quote {
+            Some(quote) => {
+                if c == '"' || (quote == '“' && c == '”') {
+                    if c == '”' { record(('”', '"', "punct.rquote")); }
+                    fixed.push('"');
+                    open_quote = None;
                 } else {
-                    eprintln!("变量声明语法错误: {}", cmd);
+                    fixed.push(c);
                 }
             }
-            // 检查是否为直接写了自定义函数名而未用 call 调用（需排除块结构、声明、say等所有已知语法）
-            else if functions.contains_key(cmd)
-                && !cmd.starts_with("call ")
-                && !cmd.starts_with("if ")
-                && !cmd.starts_with("while ")
-                && !cmd.starts_with("for ")
-                && !cmd.starts_with("do ")
-                && !cmd.starts_with("switch ")
-                && !cmd.starts_with("var ")
-                && !cmd.starts_with("const ")
-                && !cmd.starts_with("list ")
-                && !cmd.starts_with("say ")
-                && !cmd.contains("=")
-            {
-                eprintln!("请使用 call 语法调用函数: call {}", cmd);
-            }
-            // 缩进减少即块结束
-            if !jumped {
-                i += 1;
-            }
+            None => match FULLWIDTH_PUNCTUATION.iter().find(|(full, _, _)| *full == c) {
+                Some(&entry) => {
+                    record(entry);
+                    if entry.1 == '"' { open_quote = Some(c); }
+                    fixed.push(entry.1);
+                }
+                None => {
+                    if c == '"' { open_quote = Some(c); }
+                    fixed.push(c);
+                }
+            },
         }
     }
+    (fixed, replaced)
+}
 
-    fn eval_math_expr(expr: &str, variables: &HashMap<String, String>, constants: &HashMap<String, String>) -> f64 {
-        let expr = expr.trim();
-        let ops = ["+", "-", "*", "/", "%"];
-        for op in &ops {
-            if let Some((left, right)) = expr.split_once(op) {
-                let l = left.trim();
-                let r = right.trim();
-                let lval = variables.get(l).or_else(|| constants.get(l)).map(|s| s.as_str()).unwrap_or(l);
-                let rval = variables.get(r).or_else(|| constants.get(r)).map(|s| s.as_str()).unwrap_or(r);
-                let lnum = lval.parse::<f64>().unwrap_or(0.0);
-                let rnum = rval.parse::<f64>().unwrap_or(0.0);
-                return match *op {
-                    "+" => lnum + rnum,
-                    "-" => lnum - rnum,
-                    "*" => lnum * rnum,
-                    "/" => lnum / rnum,
-                    "%" => lnum % rnum,
-                    _ => 0.0,
-                };
-            }
+// 字符串之外第一个注释（#、// 或 /*）开始的位置
+fn comment_start(line: &str) -> Option<usize> {
+    let mut scan = StrScanner::default();
+    line.char_indices().find(|&(idx, c)| {
+        !scan.step(c) && (c == '#' || line[idx..].starts_with("//") || line[idx..].starts_with("/*"))
+    }).map(|(idx, _)| idx)
+}
+
+// --fix：改写文件中的全角符号（注释不动）；给出 --keywords 时同时把关键字改写为该集合，返回改动的行数
+fn fix_file(filename: &str, keywords: Option<Keywords>) -> Result<usize, HcError> {
+    let content = std::fs::read_to_string(filename)
+        .map_err(|e| HcError::of("E0701", &[&filename, &e]))?;
+    let mut in_header = true;
+    let mut has_pragma = false;
+    // 跨行的三引号字符串和块注释从第二行起原样保留
+    let mut open_span: Option<String> = None;
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(|line| {
+        if let Some(text) = open_span.as_mut() {
+            text.push_str(line);
+            if !matches!(lex_line(text), Err(("E0025" | "E0028", _))) { open_span = None; }
+            return line.to_string();
         }
-        let val = variables.get(expr).or_else(|| constants.get(expr)).map(|s| s.as_str()).unwrap_or(expr);
-        val.parse::<f64>().unwrap_or(0.0)
-    }
-
-    fn eval_condition(cond: &str, variables: &HashMap<String, String>, constants: &HashMap<String, String>) -> bool {
-        let cond = cond.replace(">=", ">=").replace("<=", "<=").replace("==", "==").replace("!=", "!=");
-        let ops = [">=", "<=", "==", "!=", ">", "<"];
-        for op in &ops {
-            if let Some((left, right)) = cond.split_once(op) {
-                let l = left.trim();
-                let r = right.trim();
-                let lval = variables.get(l).or_else(|| constants.get(l)).map(|s| s.as_str()).unwrap_or(l);
-                let rval = variables.get(r).or_else(|| constants.get(r)).map(|s| s.as_str()).unwrap_or(r);
-                match *op {
-                    ">=" => return lval >= rval,
-                    "<=" => return lval <= rval,
-                    "==" => return lval == rval,
-                    "!=" => return lval != rval,
-                    ">" => return lval > rval,
-                    "<" => return lval < rval,
-                    _ => {}
+        let is_comment = line.trim_start().starts_with('#');
+        in_header = in_header && (is_comment || line.trim().is_empty());
+        if is_comment {
+            // 文件开头的关键字指示改成目标集合
+            return match keywords {
+                Some(k) if in_header && keyword_pragma(line).is_some() => {
+                    has_pragma = true;
+                    format!("{}{}", k.pragma(), &line[line.trim_end().len()..])
                 }
-            }
+                _ => line.to_string(),
+            };
         }
-        false
+        if let Err(("E0025" | "E0028", _)) = lex_line(line) { open_span = Some(line.to_string()); }
+        // 行尾注释保持不变
+        let (code, comment) = line.split_at(comment_start(line).unwrap_or(line.len()));
+        let (fixed, _) = normalize_fullwidth(code);
+        let fixed = match keywords {
+            Some(k) => translate_keywords(&translate_keywords(&fixed, Keywords::En), k),
+            None => fixed,
+        };
+        fixed + comment
+    }).collect();
+    let mut count = content.split_inclusive('\n').zip(&lines).filter(|(a, b)| a != b).count();
+    // 改写为中文关键字时，文件开头加上关键字指示
+    if keywords == Some(Keywords::Zh) && !has_pragma {
+        lines.insert(0, format!("{}\n", Keywords::Zh.pragma()));
+        count += 1;
     }
+    if count > 0 {
+        let fixed: String = lines.concat();
+        std::fs::write(filename, fixed).map_err(|e| HcError::of("E0703", &[&filename, &e]))?;
+    }
+    Ok(count)
+}
+
+// 关键字集合：英文（默认）或中文，用文件开头的 # keywords: zh 选择
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Keywords {
+    #[default]
+    En,
+    Zh,
+}
 
-    eval_block(&start_commands, &functions, &mut variables, &mut constants, &mut lists, &mut var_types, &mut const_types, &function_sigs, &function_returns, 0);
-}
\ No newline at end of file
+impl Keywords {
+    fn from_name(name: &str) -> Option<Keywords> {
+        match name.trim() {
+            "en" | "英文" => Some(Keywords::En),
+            "zh" | "中文" => Some(Keywords::Zh),
+            _ => None,
+        }
+    }
+
+    fn pragma(self) -> &'static str {
+        match self {
+            Keywords::En => "# keywords: en",
+            Keywords::Zh => "# keywords: zh",
+        }
+    }
+
+    // 把代码或信息中的英文关键字按本集合显示
+    fn render(self, text: &str) -> String {
+        match self {
+            Keywords::En => text.to_string(),
+            Keywords::Zh => translate_keywords(text, Keywords::Zh),
+        }
+    }
+}
+
+// 关键字对照表：(英文, 中文)
+const KEYWORDS: [(&str, &str); 38] = [
+    ("function", "函数"), ("start", "开始"), ("end", "结束"), ("struct", "结构体"), ("enum", "枚举"),
+    ("var", "变量"), ("const", "常量"), ("say", "说"), ("call", "调用"), ("return", "返回"),
+    ("if", "如果"), ("while", "当"), ("for", "对于"), ("in", "在"), ("do", "执行"),
+    ("switch", "匹配"), ("case", "情况"), ("default", "默认"),
+    ("try", "尝试"), ("catch", "捕获"), ("finally", "最后"), ("throw", "抛出"), ("exit", "退出"), ("input", "输入"),
+    ("int", "整数"), ("double", "小数"), ("str", "字符串"), ("bool", "布尔"),
+    ("list", "列表"), ("map", "字典"), ("set", "集合"), ("deque", "队列"), ("opt", "可选"),
+    ("true", "真"), ("false", "假"), ("none", "空"), ("is not", "不是"), ("is", "是"),
+];
+
+// 文件开头的关键字指示：# keywords: zh 或 # 关键字: 中文；不是指示时返回 None，集合未知时返回写错的值
+fn keyword_pragma(line: &str) -> Option<Result<Keywords, String>> {
+    let comment = line.trim().strip_prefix('#')?.replace('：', ":");
+    let (key, value) = comment.split_once(':')?;
+    if !matches!(key.trim(), "keywords" | "关键字") { return None; }
+    Some(Keywords::from_name(value).ok_or_else(|| value.trim().to_string()))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// 把字符串字面量之外的关键字换成 to 集合中的写法；按整词替换，关键字和名字之间要有空格或符号
+fn translate_keywords(line: &str, to: Keywords) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut scan = StrScanner::default();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if scan.in_str || !is_word_char(c) {
+            scan.step(c);
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let len = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+        let word = &rest[..len];
+        rest = &rest[len..];
+        let translated = match to {
+            Keywords::En => KEYWORDS.iter().find(|(_, zh)| *zh == word).map(|(en, _)| *en),
+            // is not 是两个词，对应一个中文关键字
+            Keywords::Zh if word == "is" && rest.starts_with(char::is_whitespace)
+                && rest.trim_start().strip_prefix("not").is_some_and(|after| !after.starts_with(is_word_char)) => {
+                rest = &rest.trim_start()["not".len()..];
+                Some("不是")
+            }
+            Keywords::Zh => KEYWORDS.iter().find(|(en, _)| *en == word).map(|(_, zh)| *zh),
+        };
+        out.push_str(translated.unwrap_or(word));
+    }
+    out
+}
+
+// 未指定 --tab-width 时 Tab 按 4 列计算
+const DEFAULT_TAB_WIDTH: usize = 4;
+
+// 把行首的 Tab 展开成空格，Tab 跳到下一个 tab_width 的整数倍列
+fn expand_indent(line: &str, tab_width: usize) -> String {
+    let mut column = 0;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next().filter(|c| c.is_whitespace()) {
+        column = if c == '\t' { (column / tab_width + 1) * tab_width } else { column + 1 };
+        rest = &rest[c.len_utf8()..];
+    }
+    format!("{}{}", " ".repeat(column), rest)
+}
+
+// 检查函数体或 start 块中一行的缩进（INDENT/DEDENT）：
+// 以 : 结尾的行之后必须加深缩进，其他行之后不能加深；减少缩进时必须回到某个外层代码块的缩进
+// 有问题时返回错误码
+fn check_indent(stack: &mut Vec<usize>, opens_block: &mut bool, line: &str) -> Option<&'static str> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') { return None; }
+    let indent = indent_of(line);
+    let was_opening = std::mem::replace(opens_block, trimmed.ends_with(':'));
+    let Some(&top) = stack.last() else {
+        stack.push(indent);
+        return None;
+    };
+    if indent > top {
+        stack.push(indent);
+        return (!was_opening).then_some("E0020");
+    }
+    if was_opening {
+        return Some("E0021");
+    }
+    while stack.last().is_some_and(|&level| level > indent) {
+        stack.pop();
+    }
+    if stack.last() != Some(&indent) {
+        // 继续按这一行的缩进检查后面的行，避免连带报错
+        stack.push(indent);
+        return Some("E0022");
+    }
+    None
+}
+
+// 集合类声明关键字：(关键字, 名称在消息目录中的键, 空值字面量)
+const COLLECTION_KINDS: [(&str, &str, &str); 4] = [
+    ("list", "label.list", "[]"),
+    ("map", "label.map", "{}"),
+    ("set", "label.set", "{}"),
+    ("deque", "label.deque", "[]"),
+];
+
+// 解析 "类型 名称, ..." 形式的参数/字段列表
+// 名称为最后一个词，其余为类型（允许 list int xs 这样的多词类型）
+fn parse_params(params: &str) -> Vec<(String, String)> {
+    split_top_level(params, ',').iter()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            let s = s.trim();
+            match s.rsplit_once(char::is_whitespace) {
+                Some((ptype, pname)) => (ptype.trim().to_string(), pname.to_string()),
+                None => (s.to_string(), String::new()),
+            }
+        }).collect()
+}
+
+// 计算行缩进
+fn indent_of(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+// 收集第 i 行之后缩进更深的子块，返回子块和下一条语句的位置
+fn collect_block(block: &[SourceLine], i: usize, this_indent: usize) -> (Vec<SourceLine>, usize) {
+    let mut inner_block = Vec::new();
+    let mut j = i + 1;
+    while j < block.len() {
+        if indent_of(&block[j]) <= this_indent { break; }
+        inner_block.push(block[j].clone());
+        j += 1;
+    }
+    (inner_block, j)
+}
+
+// 类型检查：值（字面量形式）是否符合声明类型
+fn check_type(val: &str, vtype: &VarType) -> bool {
+    match vtype {
+        VarType::Int => val.parse::<i64>().is_ok(),
+        VarType::Double => val.parse::<f64>().is_ok(),
+        VarType::Str => is_string_literal(val),
+        VarType::Bool => val == "true" || val == "false",
+        VarType::List(inner) | VarType::Deque(inner) => match parse_list_value(val) {
+            Some(items) => items.iter().all(|item| check_type(item, inner)),
+            None => false,
+        },
+        VarType::Set(inner) => match parse_set_value(val).or_else(|| parse_list_value(val)) {
+            Some(items) => items.iter().all(|item| check_type(item, inner)),
+            None => false,
+        },
+        VarType::Map(key, value) => match parse_map_value(val) {
+            Some(entries) => entries.iter().all(|(k, v)| check_type(k, key) && check_type(v, value)),
+            None => false,
+        },
+        VarType::Tuple(types) => match parse_tuple_value(val) {
+            Some(items) => items.len() == types.len() && items.iter().zip(types).all(|(item, t)| check_type(item, t)),
+            None => false,
+        },
+        VarType::Opt(inner) => val == "none" || check_type(val, inner),
+        VarType::Named(name) => parse_struct_value(val).is_some_and(|(n, _)| n == *name)
+            || parse_enum_value(val).is_some_and(|(n, _, _)| n == *name),
+        VarType::Unknown => true,
+    }
+}
+
+// 类型推断：由值（字面量形式）得到类型，none 和空集合的元素类型无法推断，为 Unknown
+fn value_type(val: &str) -> VarType {
+    let items_type = |items: Vec<String>| Box::new(common_type(items.iter().map(|item| value_type(item)).collect()));
+    if is_string_literal(val) { return VarType::Str; }
+    if val.parse::<i64>().is_ok() { return VarType::Int; }
+    if val.parse::<f64>().is_ok() { return VarType::Double; }
+    if val == "true" || val == "false" { return VarType::Bool; }
+    if val == "none" { return VarType::Opt(Box::new(VarType::Unknown)); }
+    if let Some(items) = parse_list_value(val) { return VarType::List(items_type(items)); }
+    if let Some(entries) = parse_map_value(val) {
+        let (keys, values): (Vec<String>, Vec<String>) = entries.into_iter().unzip();
+        return VarType::Map(items_type(keys), items_type(values));
+    }
+    if let Some(items) = parse_set_value(val) { return VarType::Set(items_type(items)); }
+    if let Some(items) = parse_tuple_value(val) { return VarType::Tuple(items.iter().map(|item| value_type(item)).collect()); }
+    if let Some((name, _, _)) = parse_enum_value(val) { return VarType::Named(name.to_string()); }
+    if let Some((name, _)) = parse_struct_value(val) { return VarType::Named(name.to_string()); }
+    VarType::Unknown
+}
+
+// 未写类型的声明：初始值是函数调用时取 ->return:: 声明的类型，否则由值推断
+fn infer_decl_type(expr: &str, val: &str, prog: &Program) -> VarType {
+    let expr = expr.trim();
+    let call_expr = expr.strip_prefix("call ").unwrap_or(expr).trim();
+    if find_arith_op(expr).is_none() && is_function_call(call_expr, prog)
+        && let Some((_, Some(ret_type))) = prog.function_sigs.get(split_call(call_expr).0)
+    {
+        return parse_type(ret_type);
+    }
+    value_type(val)
+}
+
+// 集合类声明省略元素类型时（list xs = [1, 2]），用推断出的元素类型补全
+fn refine_type(declared: VarType, inferred: VarType) -> VarType {
+    use VarType::*;
+    match (declared, inferred) {
+        (List(d), List(i)) if *d == Unknown => List(i),
+        (Deque(d), List(i) | Deque(i)) if *d == Unknown => Deque(i),
+        (Set(d), Set(i) | List(i)) if *d == Unknown => Set(i),
+        (Map(k, v), Map(ik, iv)) if *k == Unknown && *v == Unknown => Map(ik, iv),
+        (declared, _) => declared,
+    }
+}
+
+fn is_string_literal(s: &str) -> bool {
+    let mut scan = StrScanner::default();
+    let mut chars = s.char_indices();
+    if !chars.next().is_some_and(|(_, c)| scan.step(c)) { return false; }
+    // 结束字符串的引号必须是最后一个字符
+    chars.find(|&(_, c)| { scan.step(c); !scan.in_str }).is_some_and(|(idx, _)| idx + 1 == s.len())
+}
+
+// 逐字符扫描时记录是否在字符串字面量内；字符串中的 \" 和 \\ 是转义，不会结束字符串
+#[derive(Default)]
+struct StrScanner {
+    in_str: bool,
+    escaped: bool,
+}
+
+impl StrScanner {
+    // 读入一个字符，返回它是否属于字符串字面量（包括两端的引号）
+    fn step(&mut self, c: char) -> bool {
+        if !self.in_str {
+            self.in_str = c == '"';
+            return self.in_str;
+        }
+        if self.escaped {
+            self.escaped = false;
+        } else if c == '\\' {
+            self.escaped = true;
+        } else if c == '"' {
+            self.in_str = false;
+        }
+        true
+    }
+}
+
+// 字符串字面量之外的字符及其位置，供需要从右往左扫描的地方使用
+fn code_chars(s: &str) -> Vec<(usize, char)> {
+    let mut scan = StrScanner::default();
+    s.char_indices().filter(|&(_, c)| !scan.step(c)).collect()
+}
+
+// 字符串内容写成字面量：\\ \" 换行和 Tab 转义，其余字符原样保留
+fn quote_str(content: &str) -> String {
+    let mut out = String::with_capacity(content.len() + 2);
+    out.push('"');
+    for c in content.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// 输出时字符串去除引号并还原转义，其余值原样输出
+fn display_value(val: &str) -> String {
+    if !is_string_literal(val) { return val.to_string(); }
+    let mut out = String::with_capacity(val.len());
+    let mut chars = val[1..val.len()-1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' { out.push(c); continue; }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+// 返回所有位于顶层（不在括号或字符串内）的字符位置
+fn top_level_indices(s: &str) -> Vec<usize> {
+    let mut result = Vec::new();
+    let mut depth = 0i32;
+    let mut scan = StrScanner::default();
+    for (idx, c) in s.char_indices() {
+        if scan.step(c) { continue; }
+        match c {
+            '[' | '(' | '{' => depth += 1,
+            ']' | ')' | '}' => depth -= 1,
+            _ if depth == 0 => result.push(idx),
+            _ => {}
+        }
+    }
+    result
+}
+
+// 按顶层分隔符切分（忽略括号与字符串内部的分隔符）
+fn split_top_level(s: &str, sep: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for idx in top_level_indices(s) {
+        if s[idx..].starts_with(sep) {
+            parts.push(s[last..idx].trim().to_string());
+            last = idx + sep.len_utf8();
+        }
+    }
+    parts.push(s[last..].trim().to_string());
+    parts
+}
+
+// 将列表字面量 [a, b, ...] 拆成元素（元素本身可以是列表）
+fn parse_list_value(val: &str) -> Option<Vec<String>> {
+    let inner = val.trim().strip_prefix('[')?.strip_suffix(']')?;
+    Some(split_top_level(inner, ',').into_iter().filter(|s| !s.is_empty()).collect())
+}
+
+fn format_list(items: &[String]) -> String {
+    format!("[{}]", items.join(", "))
+}
+
+// 将字典字面量 {k: v, ...} 拆成有序的键值对
+fn parse_map_value(val: &str) -> Option<Entries> {
+    let inner = val.trim().strip_prefix('{')?.strip_suffix('}')?;
+    split_top_level(inner, ',').into_iter()
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let colon = top_level_indices(&entry).into_iter().find(|&idx| entry[idx..].starts_with(':'))?;
+            Some((entry[..colon].trim().to_string(), entry[colon+1..].trim().to_string()))
+        })
+        .collect()
+}
+
+fn format_map(entries: &[(String, String)]) -> String {
+    let parts: Vec<String> = entries.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+    format!("{{{}}}", parts.join(", "))
+}
+
+// 将结构体值 Name(field: v, ...) 拆成名称和有序字段
+fn parse_struct_value(val: &str) -> Option<(&str, Entries)> {
+    let val = val.trim();
+    let (name, inner) = val.strip_suffix(')')?.split_once('(')?;
+    if !is_identifier(name) || !is_balanced(inner) { return None; }
+    let fields = split_top_level(inner, ',').into_iter()
+        .filter(|s| !s.is_empty())
+        .map(|field| {
+            let (fname, fval) = field.split_once(':')?;
+            let fname = fname.trim();
+            is_identifier(fname).then(|| (fname.to_string(), fval.trim().to_string()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((name, fields))
+}
+
+fn format_struct(name: &str, fields: &[(String, String)]) -> String {
+    let parts: Vec<String> = fields.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+    format!("{}({})", name, parts.join(", "))
+}
+
+// 括号是否配对且不会提前闭合
+fn is_balanced(s: &str) -> bool {
+    let mut depth = 0i32;
+    let mut scan = StrScanner::default();
+    for c in s.chars() {
+        if scan.step(c) { continue; }
+        match c {
+            '[' | '(' | '{' => depth += 1,
+            ']' | ')' | '}' => {
+                depth -= 1;
+                if depth < 0 { return false; }
+            }
+            _ => {}
+        }
+    }
+    depth == 0 && !scan.in_str
+}
+
+// 拆出末尾的字段访问 base.field（数字小数点不算）
+fn split_field_access(expr: &str) -> Option<(&str, &str)> {
+    let dot = top_level_indices(expr).into_iter().rev().find(|&idx| expr[idx..].starts_with('.'))?;
+    let (base, field) = (expr[..dot].trim(), expr[dot+1..].trim());
+    if base.is_empty() || !is_identifier(field) || field.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((base, field))
+}
+
+// 构造结构体或带载荷的枚举成员：参数可以按顺序给出，也可以写成 字段: 值
+fn construct_struct(name: &str, fields: &[(String, String)], args: &str, prog: &Program, rt: &mut Runtime) -> Result<String, HcError> {
+    let args: Vec<String> = split_top_level(args, ',').into_iter().filter(|s| !s.is_empty()).collect();
+    let named: Vec<(&str, &str)> = args.iter()
+        .filter_map(|arg| arg.split_once(':').map(|(k, v)| (k.trim(), v.trim())).filter(|(k, _)| is_identifier(k)))
+        .collect();
+    let by_name = !args.is_empty() && named.len() == args.len();
+    if args.len() > fields.len() {
+        return Err(HcError::of("E0108", &[&name, &fields.len(), &args.len()]));
+    }
+    if let Some((unknown, _)) = named.iter().find(|(k, _)| by_name && !fields.iter().any(|(_, f)| f == k)) {
+        return Err(HcError::of("E0206", &[&name, &unknown]));
+    }
+    let mut values = Vec::new();
+    for (idx, (ftype, fname)) in fields.iter().enumerate() {
+        let expr = if by_name {
+            named.iter().find(|(k, _)| k == fname).map(|(_, v)| *v)
+        } else {
+            args.get(idx).map(|s| s.as_str())
+        };
+        let expr = expr.ok_or_else(|| HcError::of("E0207", &[&name, &fname]))?;
+        let val = eval_value(expr, prog, rt)?;
+        if !check_type(&val, &parse_type(ftype)) {
+            return Err(HcError::of("E0101", &[&tr("what.field", &[&name, &fname]), &val]));
+        }
+        values.push((fname.clone(), val));
+    }
+    Ok(format_struct(name, &values))
+}
+
+// 将元组 (a, b, ...) 拆成元素；至少两个元素，单个元素要写成 (a,)
+fn parse_tuple_value(val: &str) -> Option<Vec<String>> {
+    let inner = val.trim().strip_prefix('(')?.strip_suffix(')')?;
+    if !is_balanced(inner) { return None; }
+    let items = split_top_level(inner, ',');
+    if items.len() < 2 { return None; }
+    let single = items.len() == 2 && items[1].is_empty();
+    let items: Vec<String> = items.into_iter().filter(|s| !s.is_empty()).collect();
+    (single || items.len() >= 2).then_some(items)
+}
+
+fn format_tuple(items: &[String]) -> String {
+    if items.len() == 1 {
+        format!("({},)", items[0])
+    } else {
+        format!("({})", items.join(", "))
+    }
+}
+
+// 将枚举值 Color.Red 或 Shape.Circle(r: 1.5) 拆成枚举名、成员名和载荷
+fn parse_enum_value(val: &str) -> Option<(&str, &str, Entries)> {
+    let (enum_name, rest) = val.trim().split_once('.')?;
+    if !is_identifier(enum_name) || enum_name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    if is_identifier(rest) {
+        return Some((enum_name, rest, Vec::new()));
+    }
+    let (variant, fields) = parse_struct_value(rest)?;
+    Some((enum_name, variant, fields))
+}
+
+// 求值枚举成员 Color.Red 或 Shape.Circle(1.5)；不是已定义枚举时返回 None
+fn eval_enum_variant(expr: &str, prog: &Program, rt: &mut Runtime) -> Option<Result<String, HcError>> {
+    let (enum_name, rest) = expr.split_once('.')?;
+    let variants = prog.enums.get(enum_name.trim())?;
+    let (variant, args) = split_call(rest.trim());
+    let qualified = format!("{}.{}", enum_name.trim(), variant);
+    let Some((_, fields)) = variants.iter().find(|(v, _)| v == variant) else {
+        return Some(Err(HcError::of("E0208", &[&enum_name.trim(), &variant])));
+    };
+    if fields.is_empty() {
+        if !args.trim().is_empty() {
+            return Some(Err(HcError::of("E0109", &[&qualified])));
+        }
+        return Some(Ok(qualified));
+    }
+    Some(construct_struct(&qualified, fields, args, prog, rt))
+}
+
+// 将集合字面量 {a, b, ...} 拆成元素（去重，保持首次出现的顺序）；带冒号的是字典
+fn parse_set_value(val: &str) -> Option<Vec<String>> {
+    let inner = val.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut items: Vec<String> = Vec::new();
+    for item in split_top_level(inner, ',').into_iter().filter(|s| !s.is_empty()) {
+        if top_level_indices(&item).into_iter().any(|idx| item[idx..].starts_with(':')) {
+            return None;
+        }
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+    Some(items)
+}
+
+fn format_set(items: &[String]) -> String {
+    format!("{{{}}}", items.join(", "))
+}
+
+// 拆出末尾的下标访问 base[index]，列表字面量本身不算
+fn split_index_suffix(expr: &str) -> Option<(&str, &str)> {
+    if !expr.ends_with(']') { return None; }
+    let mut depth = 0i32;
+    for (idx, c) in code_chars(expr).into_iter().rev() {
+        match c {
+            ']' | ')' | '}' => depth += 1,
+            '[' | '(' | '{' => {
+                depth -= 1;
+                if depth == 0 {
+                    if idx == 0 || c != '[' { return None; }
+                    return Some((expr[..idx].trim(), &expr[idx+1..expr.len()-1]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// 拆出末尾的方法调用 base.method(args)
+fn split_method_call(expr: &str) -> Option<(&str, &str, &str)> {
+    let body = expr.strip_suffix(')')?;
+    let mut depth = 0i32;
+    let mut lparen = None;
+    for (idx, c) in code_chars(body).into_iter().rev() {
+        match c {
+            ']' | ')' | '}' => depth += 1,
+            '[' | '{' => depth -= 1,
+            '(' => {
+                if depth == 0 { lparen = Some(idx); break; }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    let lparen = lparen?;
+    let (base, method) = body[..lparen].rsplit_once('.')?;
+    let method = method.trim();
+    if base.trim().is_empty() || method.is_empty() || !method.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((base.trim(), method, &body[lparen+1..]))
+}
+
+// 不带 call 的函数调用 f(x)：名字是已定义的函数，并且整个表达式就是这一次调用
+fn is_function_call(expr: &str, prog: &Program) -> bool {
+    call_name(expr).is_some_and(|fname| prog.functions.contains_key(fname))
+}
+
+// 形如 名字(参数) 的表达式的名字：与名字后面的 ( 配对的 ) 必须是最后一个字符
+fn call_name(expr: &str) -> Option<&str> {
+    let lparen = expr.find('(')?;
+    let name = expr[..lparen].trim();
+    (is_identifier(name) && closing_paren(expr, lparen) == Some(expr.len() - 1)).then_some(name)
+}
+
+// 与 open 处的左括号配对的右括号位置，字符串中的括号不算；没有配对的右括号时返回 None
+fn closing_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    code_chars(&s[open..]).into_iter().find(|&(_, c)| {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    }).map(|(idx, _)| open + idx)
+}
+
+// 拆分 f(a, b) 形式的调用为函数名和参数串；参数串到与第一个 ( 配对的 ) 为止，缺少 ) 时到末尾
+fn split_call(call_expr: &str) -> (&str, &str) {
+    let Some((lparen, _)) = code_chars(call_expr).into_iter().find(|&(_, c)| c == '(') else {
+        return (call_expr, "");
+    };
+    let rparen = closing_paren(call_expr, lparen).unwrap_or(call_expr.len());
+    (call_expr[..lparen].trim(), &call_expr[lparen+1..rparen])
+}
+
+fn list_index(idx: &str, len: usize) -> Result<usize, HcError> {
+    let n = idx.parse::<usize>().map_err(|_| HcError::of("E0301", &[&idx]))?;
+    if n >= len {
+        return Err(HcError::of("E0401", &[&n, &len]));
+    }
+    Ok(n)
+}
+
+// 下标读取：列表按位置，字典按键
+fn index_value(container: &str, idx: &str) -> Result<String, HcError> {
+    if let Some(items) = parse_list_value(container).or_else(|| parse_tuple_value(container)) {
+        return Ok(items[list_index(idx, items.len())?].clone());
+    }
+    if let Some(entries) = parse_map_value(container) {
+        return entries.into_iter().find(|(k, _)| k == idx).map(|(_, v)| v)
+            .ok_or_else(|| HcError::of("E0501", &[&idx]));
+    }
+    Err(HcError::of("E0110", &[&container]))
+}
+
+// 在嵌套的列表/字典中按下标路径写入新值，返回更新后的容器；字典中不存在的键会追加到末尾
+fn set_path(container: &str, path: &[String], new_val: String) -> Result<String, HcError> {
+    let Some((key, rest)) = path.split_first() else { return Ok(new_val) };
+    if let Some(mut items) = parse_list_value(container) {
+        let n = list_index(key, items.len())?;
+        items[n] = set_path(&items[n], rest, new_val)?;
+        return Ok(format_list(&items));
+    }
+    if let Some(mut entries) = parse_map_value(container) {
+        match entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = set_path(&entry.1, rest, new_val)?,
+            None if rest.is_empty() => entries.push((key.clone(), new_val)),
+            None => return Err(HcError::of("E0501", &[&key])),
+        }
+        return Ok(format_map(&entries));
+    }
+    if let Some((name, mut fields)) = parse_struct_value(container) {
+        let field = fields.iter_mut().find(|(k, _)| k == key)
+            .ok_or_else(|| HcError::of("E0206", &[&name, &key]))?;
+        field.1 = set_path(&field.1, rest, new_val)?;
+        return Ok(format_struct(name, &fields));
+    }
+    Err(HcError::of("E0111", &[&container]))
+}
+
+// 沿下标/字段路径检查键类型，并返回最终元素的声明类型
+fn path_type(vtype: &VarType, path: &[String], prog: &Program) -> Result<VarType, HcError> {
+    let mut vtype = vtype.clone();
+    for key in path {
+        vtype = match vtype {
+            VarType::List(inner) => *inner,
+            VarType::Map(key_type, value_type) => {
+                if !check_type(key, &key_type) {
+                    return Err(HcError::of("E0113", &[&key]));
+                }
+                *value_type
+            }
+            VarType::Named(name) => match prog.structs.get(&name).and_then(|fields| fields.iter().find(|(_, f)| f == key)) {
+                Some((ftype, _)) => parse_type(ftype),
+                None => return Err(HcError::of("E0206", &[&name, &key])),
+            },
+            _ => return Ok(VarType::Unknown),
+        };
+    }
+    Ok(vtype)
+}
+
+// 方法调用：命名的字典/集合/双端队列先交给方法模块的修改类方法，其余方法对求值后的值只读调用
+fn call_method(base: &str, method: &str, args: &str, prog: &Program, rt: &mut Runtime) -> Result<String, HcError> {
+    let arg_vals = eval_args(args, prog, rt)?;
+    if MUTATING_METHODS.contains(&method) {
+        check_not_const(base, |name| is_runtime_const(rt, name))?;
+    }
+    if is_identifier(base) && let Some(result) = METHOD_MODULES.iter()
+        .filter_map(|module| module.call_mut)
+        .find_map(|call_mut| call_mut(base, method, &arg_vals, rt)) {
+        return result;
+    }
+    let container = require_value(eval_value(base, prog, rt)?, base)?;
+    call_value_method(&container, method, &arg_vals)
+}
+
+// 会修改容器的方法
+const MUTATING_METHODS: [&str; 6] = ["remove", "add", "push_back", "push_front", "pop_back", "pop_front"];
+
+// 只读方法：按值的类型交给内置方法模块，没有模块支持时报告错误
+fn call_value_method(container: &str, method: &str, args: &[String]) -> Result<String, HcError> {
+    if let Some(result) = METHOD_MODULES.iter().find_map(|module| (module.call)(container, method, args)) {
+        return result;
+    }
+    match (method, args) {
+        ("len", []) => Err(HcError::of("E0114", &[&container])),
+        ("has", [_]) if parse_list_value(container).is_none() => Err(HcError::of("E0115", &[&container])),
+        _ if MUTATING_METHODS.contains(&method) => Err(HcError::of("E0117", &[&method])),
+        _ => Err(HcError::of("E0118", &[&container, &method])),
+    }
+}
+
+// 方法调用的结果；None 表示接收者不支持这个方法
+type MethodResult = Option<Result<String, HcError>>;
+
+// 修改命名容器的方法：参数依次是容器名、方法名、求值后的参数和运行时状态
+type MutMethod = fn(&str, &str, &[String], &mut Runtime) -> MethodResult;
+
+// 内置方法模块：call 对求值后的值调用方法，call_mut 对命名的容器调用修改类方法，
+// infer 按接收者类型推断结果类型（供 hitcode check 使用）；
+// 值的类型或方法不属于这个模块时返回 None。新的内置方法按模块登记在 METHOD_MODULES 中
+struct MethodModule {
+    call: fn(&str, &str, &[String]) -> MethodResult,
+    call_mut: Option<MutMethod>,
+    infer: fn(&VarType, &str, &[VarType]) -> Option<VarType>,
+}
+
+const METHOD_MODULES: &[MethodModule] = &[
+    MethodModule { call: str_method, call_mut: None, infer: infer_str_method },
+    MethodModule { call: number_method, call_mut: None, infer: infer_number_method },
+    MethodModule { call: list_method, call_mut: None, infer: infer_list_method },
+    MethodModule { call: map_method, call_mut: Some(map_method_mut), infer: infer_map_method },
+    MethodModule { call: set_method, call_mut: Some(set_method_mut), infer: infer_set_method },
+    // 双端队列求值后是列表，只读方法由列表模块处理
+    MethodModule { call: |_, _, _| None, call_mut: Some(deque_method_mut), infer: infer_deque_method },
+    MethodModule { call: any_method, call_mut: None, infer: infer_any_method },
+];
+
+// 字符串方法：len upper lower trim contains starts_with ends_with replace split to_int to_double
+fn str_method(val: &str, method: &str, args: &[String]) -> MethodResult {
+    if !is_string_literal(val) { return None; }
+    let text = display_value(val);
+    let args: Vec<String> = args.iter().filter(|a| is_string_literal(a)).map(|a| display_value(a)).collect();
+    let convert = |target: &str| HcError::of("E0303", &[&val, &target]);
+    Some(match (method, args.as_slice()) {
+        ("len", []) => Ok(text.chars().count().to_string()),
+        ("upper", []) => Ok(quote_str(&text.to_uppercase())),
+        ("lower", []) => Ok(quote_str(&text.to_lowercase())),
+        ("trim", []) => Ok(quote_str(text.trim())),
+        ("contains", [sub]) => Ok(text.contains(sub.as_str()).to_string()),
+        ("starts_with", [prefix]) => Ok(text.starts_with(prefix.as_str()).to_string()),
+        ("ends_with", [suffix]) => Ok(text.ends_with(suffix.as_str()).to_string()),
+        ("replace", [from, to]) => Ok(quote_str(&text.replace(from.as_str(), to))),
+        ("split", [sep]) if !sep.is_empty() => Ok(format_list(&text.split(sep.as_str()).map(quote_str).collect::<Vec<_>>())),
+        ("to_int", []) => text.trim().parse::<i64>().map(|n| n.to_string()).map_err(|_| convert("int")),
+        ("to_double", []) => text.trim().parse::<f64>().map(|n| n.to_string()).map_err(|_| convert("double")),
+        _ => return None,
+    })
+}
+
+fn infer_str_method(base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    use VarType::*;
+    if *base_type != Str || !args.iter().all(|a| assignable(a, &Str)) { return None; }
+    match (method, args.len()) {
+        ("len" | "to_int", 0) => Some(Int),
+        ("to_double", 0) => Some(Double),
+        ("upper" | "lower" | "trim", 0) | ("replace", 2) => Some(Str),
+        ("contains" | "starts_with" | "ends_with", 1) => Some(Bool),
+        ("split", 1) => Some(List(Box::new(Str))),
+        _ => None,
+    }
+}
+
+// 数字方法：abs floor ceil round pow to_int to_double
+fn number_method(val: &str, method: &str, args: &[String]) -> MethodResult {
+    let n = val.parse::<f64>().ok()?;
+    let args: Vec<f64> = args.iter().filter_map(|a| a.parse::<f64>().ok()).collect();
+    let result = match (method, args.as_slice()) {
+        ("abs", []) => n.abs(),
+        ("floor", []) => n.floor(),
+        ("ceil", []) => n.ceil(),
+        ("round", []) => n.round(),
+        ("to_int", []) => n.trunc(),
+        ("to_double", []) => n,
+        ("pow", [exp]) => n.powf(*exp),
+        _ => return None,
+    };
+    Some(Ok(result.to_string()))
+}
+
+fn infer_number_method(base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    use VarType::*;
+    if !matches!(base_type, Int | Double) || !args.iter().all(|a| assignable(a, &Double)) { return None; }
+    match (method, args.len()) {
+        ("abs", 0) => Some(base_type.clone()),
+        ("floor" | "ceil" | "round" | "to_int", 0) => Some(Int),
+        ("to_double", 0) | ("pow", 1) => Some(Double),
+        _ => None,
+    }
+}
+
+// 列表方法：len contains index_of join reversed sorted
+fn list_method(val: &str, method: &str, args: &[String]) -> MethodResult {
+    let mut items = parse_list_value(val)?;
+    Some(Ok(match (method, args) {
+        ("len", []) => items.len().to_string(),
+        ("contains", [item]) => items.contains(item).to_string(),
+        ("index_of", [item]) => items.iter().position(|x| x == item).map_or(-1, |pos| pos as i64).to_string(),
+        ("join", [sep]) if is_string_literal(sep) => {
+            quote_str(&items.iter().map(|item| display_value(item)).collect::<Vec<_>>().join(&display_value(sep)))
+        }
+        ("reversed", []) => {
+            items.reverse();
+            format_list(&items)
+        }
+        ("sorted", []) => {
+            items.sort_by(|a, b| compare_values(a, b));
+            format_list(&items)
+        }
+        _ => return None,
+    }))
+}
+
+fn infer_list_method(base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    use VarType::*;
+    let (List(inner) | Deque(inner)) = base_type else { return None };
+    match (method, args) {
+        ("len", []) => Some(Int),
+        ("contains", [item]) if assignable(item, inner) => Some(Bool),
+        ("index_of", [item]) if assignable(item, inner) => Some(Int),
+        ("join", [sep]) if assignable(sep, &Str) => Some(Str),
+        ("reversed" | "sorted", []) => Some(List(inner.clone())),
+        _ => None,
+    }
+}
+
+// 字典方法：len has get keys values；命名的字典还有 remove
+fn map_method(val: &str, method: &str, args: &[String]) -> MethodResult {
+    let entries = parse_map_value(val)?;
+    Some(Ok(match (method, args) {
+        ("len", []) => entries.len().to_string(),
+        ("has", [key]) => entries.iter().any(|(k, _)| k == key).to_string(),
+        ("get", [key]) => entries.into_iter().find(|(k, _)| k == key).map_or_else(|| "none".to_string(), |(_, v)| v),
+        ("keys", []) => format_list(&entries.into_iter().map(|(k, _)| k).collect::<Vec<_>>()),
+        ("values", []) => format_list(&entries.into_iter().map(|(_, v)| v).collect::<Vec<_>>()),
+        _ => return None,
+    }))
+}
+
+fn map_method_mut(base: &str, method: &str, args: &[String], rt: &mut Runtime) -> MethodResult {
+    let entries = rt.maps.get_mut(base)?;
+    match (method, args) {
+        ("remove", [key]) => Some(match entries.iter().position(|(k, _)| k == key) {
+            Some(pos) => Ok(entries.remove(pos).1),
+            None => Err(HcError::of("E0502", &[&base, &key])),
+        }),
+        _ => None,
+    }
+}
+
+fn infer_map_method(base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    use VarType::*;
+    let Map(key, value) = base_type else { return None };
+    match (method, args) {
+        ("len", []) => Some(Int),
+        ("has", [_]) => Some(Bool),
+        ("get", [_]) => Some(Opt(value.clone())),
+        ("keys", []) => Some(List(key.clone())),
+        ("values", []) => Some(List(value.clone())),
+        ("remove", [_]) => Some((**value).clone()),
+        _ => None,
+    }
+}
+
+// 集合方法：len has union intersection difference；命名的集合还有 add remove
+fn set_method(val: &str, method: &str, args: &[String]) -> MethodResult {
+    let items = parse_set_value(val)?;
+    Some(match (method, args) {
+        ("len", []) => Ok(items.len().to_string()),
+        ("has", [item]) => Ok(items.contains(item).to_string()),
+        ("union" | "intersection" | "difference", [other]) => parse_set_value(other).or_else(|| parse_list_value(other))
+            .ok_or_else(|| HcError::of("E0116", &[&method, &other]))
+            .map(|other| {
+                let result: Vec<String> = match method {
+                    "union" => items.into_iter().chain(other).collect(),
+                    "intersection" => items.into_iter().filter(|x| other.contains(x)).collect(),
+                    _ => items.into_iter().filter(|x| !other.contains(x)).collect(),
+                };
+                format_set(&parse_set_value(&format_set(&result)).unwrap_or_default())
+            }),
+        _ => return None,
+    })
+}
+
+fn set_method_mut(base: &str, method: &str, args: &[String], rt: &mut Runtime) -> MethodResult {
+    let check = elem_check(base, rt);
+    let items = rt.sets.get_mut(base)?;
+    match (method, args) {
+        ("add", [item]) => Some(check(item).map(|_| {
+            if !items.contains(item) { items.push(item.clone()); }
+            String::new()
+        })),
+        ("remove", [item]) => Some(match items.iter().position(|x| x == item) {
+            Some(pos) => Ok(items.remove(pos)),
+            None => Err(HcError::of("E0503", &[&base, &item])),
+        }),
+        _ => None,
+    }
+}
+
+fn infer_set_method(base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    use VarType::*;
+    let Set(inner) = base_type else { return None };
+    match (method, args) {
+        ("len", []) => Some(Int),
+        ("has", [_]) => Some(Bool),
+        ("union" | "intersection" | "difference", [_]) => Some(base_type.clone()),
+        // 加入的元素类型由 infer_method 检查
+        ("add", [_]) => Some(Unknown),
+        ("remove", [_]) => Some((**inner).clone()),
+        _ => None,
+    }
+}
+
+// 双端队列的修改类方法：push_back push_front pop_back pop_front
+fn deque_method_mut(base: &str, method: &str, args: &[String], rt: &mut Runtime) -> MethodResult {
+    let check = elem_check(base, rt);
+    let items = rt.deques.get_mut(base)?;
+    let empty = || HcError::of("E0403", &[&base]);
+    match (method, args) {
+        ("push_back", [item]) => Some(check(item).map(|_| { items.push_back(item.clone()); String::new() })),
+        ("push_front", [item]) => Some(check(item).map(|_| { items.push_front(item.clone()); String::new() })),
+        ("pop_back", []) => Some(items.pop_back().ok_or_else(empty)),
+        ("pop_front", []) => Some(items.pop_front().ok_or_else(empty)),
+        _ => None,
+    }
+}
+
+fn infer_deque_method(base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    use VarType::*;
+    let Deque(inner) = base_type else { return None };
+    match (method, args) {
+        ("push_back" | "push_front", [_]) => Some(Unknown),
+        ("pop_back" | "pop_front", []) => Some((**inner).clone()),
+        _ => None,
+    }
+}
+
+// 检查加入命名集合/双端队列的元素是否符合声明的元素类型
+fn elem_check(base: &str, rt: &Runtime) -> impl Fn(&str) -> Result<(), HcError> + use<> {
+    let elem_type = match rt.var_types.get(base) {
+        Some(VarType::Set(inner)) | Some(VarType::Deque(inner)) => (**inner).clone(),
+        _ => VarType::Unknown,
+    };
+    let base = base.to_string();
+    move |item| if check_type(item, &elem_type) { Ok(()) } else { Err(HcError::of("E0103", &[&base, &item])) }
+}
+
+// 所有值都有的方法：to_str 得到 say 输出的文字
+fn any_method(val: &str, method: &str, args: &[String]) -> MethodResult {
+    match (method, args) {
+        ("to_str", []) => Some(Ok(quote_str(&display_value(val)))),
+        _ => None,
+    }
+}
+
+fn infer_any_method(_base_type: &VarType, method: &str, args: &[VarType]) -> Option<VarType> {
+    (method == "to_str" && args.is_empty()).then_some(VarType::Str)
+}
+
+// 求值表达式，结果以字面量形式返回（字符串带引号，列表为 [a, b]）
+fn eval_value(expr: &str, prog: &Program, rt: &mut Runtime) -> Result<String, HcError> {
+    let expr = expr.trim();
+    if expr.is_empty() { return Err(HcError::of("E0010", &[])); }
+    if is_string_literal(expr) { return Ok(expr.to_string()); }
+    if let Some((pos, op)) = find_arith_op(expr) {
+        let l = require_value(eval_value(&expr[..pos], prog, rt)?, &expr[..pos])?;
+        let r = require_value(eval_value(&expr[pos+1..], prog, rt)?, &expr[pos+1..])?;
+        let (Ok(lnum), Ok(rnum)) = (l.parse::<f64>(), r.parse::<f64>()) else {
+            return Err(HcError::of("E0120", &[&expr]));
+        };
+        if matches!(op, '/' | '%') && rnum == 0.0 {
+            return Err(HcError::of("E0601", &[&expr]));
+        }
+        let result = match op {
+            '+' => lnum + rnum,
+            '-' => lnum - rnum,
+            '*' => lnum * rnum,
+            '/' => lnum / rnum,
+            _ => lnum % rnum,
+        };
+        return Ok(result.to_string());
+    }
+    // 函数调用：call f(x) 或直接写 f(x)；call f(x).m() 等去掉 call 后按普通表达式求值
+    if let Some(rest) = expr.strip_prefix("call ") {
+        let rest = rest.trim();
+        return match call_name(rest) {
+            Some(_) => call_function(rest, prog, rt),
+            None => eval_value(rest, prog, rt),
+        };
+    }
+    if is_function_call(expr, prog) {
+        return call_function(expr, prog, rt);
+    }
+    if let Some(operand) = expr.strip_prefix('-') {
+        let v = require_value(eval_value(operand, prog, rt)?, operand)?;
+        return v.parse::<f64>().map(|n| (-n).to_string()).map_err(|_| HcError::of("E0120", &[&expr]));
+    }
+    if let Some(result) = eval_enum_variant(expr, prog, rt) {
+        return result;
+    }
+    if is_input_call(expr) {
+        return read_input(expr, "str");
+    }
+    if let Some((base, method, args)) = split_method_call(expr) {
+        return call_method(base, method, args, prog, rt);
+    }
+    if let Some((base, field)) = split_field_access(expr) {
+        let base_val = require_value(eval_value(base, prog, rt)?, base)?;
+        let (name, fields) = parse_struct_value(&base_val).ok_or_else(|| HcError::of("E0129", &[&base, &field]))?;
+        return fields.into_iter().find(|(k, _)| k == field).map(|(_, v)| v)
+            .ok_or_else(|| HcError::of("E0206", &[&name, &field]));
+    }
+    if let Some((base, idx)) = split_index_suffix(expr) {
+        let base_val = require_value(eval_value(base, prog, rt)?, base)?;
+        let idx_val = eval_value(idx, prog, rt)?;
+        return index_value(&base_val, &idx_val);
+    }
+    if expr.ends_with(')') && let (name, args) = split_call(expr) && let Some(fields) = prog.structs.get(name) {
+        return construct_struct(name, fields, args, prog, rt);
+    }
+    if let Some(items) = parse_list_value(expr) {
+        let vals = items.iter().map(|item| eval_value(item, prog, rt)).collect::<Result<Vec<_>, _>>()?;
+        return Ok(format_list(&vals));
+    }
+    if let Some(entries) = parse_map_value(expr) {
+        let vals = entries.iter()
+            .map(|(k, v)| Ok((eval_value(k, prog, rt)?, eval_value(v, prog, rt)?)))
+            .collect::<Result<Vec<_>, HcError>>()?;
+        return Ok(format_map(&vals));
+    }
+    if let Some(items) = parse_set_value(expr) {
+        let vals = items.iter().map(|item| eval_value(item, prog, rt)).collect::<Result<Vec<_>, _>>()?;
+        return Ok(format_set(&parse_set_value(&format_set(&vals)).unwrap_or_default()));
+    }
+    if let Some(items) = parse_tuple_value(expr) {
+        let vals = items.iter().map(|item| eval_value(item, prog, rt)).collect::<Result<Vec<_>, _>>()?;
+        return Ok(format_tuple(&vals));
+    }
+    if let Some(inner) = expr.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        return eval_value(inner, prog, rt);
+    }
+    // trace() 返回当前的调用栈，最近的调用在前
+    if expr == "trace()" {
+        let lines: Vec<String> = stack_lines(prog, &rt.frames, rt.line).iter().map(|l| quote_str(l)).collect();
+        return Ok(format_list(&lines));
+    }
+    if expr.parse::<f64>().is_ok() || expr == "true" || expr == "false" || expr == "none" {
+        return Ok(expr.to_string());
+    }
+    if let Some(val) = rt.variables.get(expr).or_else(|| rt.constants.get(expr)) {
+        return Ok(val.clone());
+    }
+    if let Some(items) = rt.lists.get(expr) {
+        return Ok(format_list(items));
+    }
+    if let Some(entries) = rt.maps.get(expr) {
+        return Ok(format_map(entries));
+    }
+    if let Some(items) = rt.sets.get(expr) {
+        return Ok(format_set(items));
+    }
+    if let Some(items) = rt.deques.get(expr) {
+        return Ok(format_list(&items.iter().cloned().collect::<Vec<_>>()));
+    }
+    if let Some(fname) = call_name(expr) {
+        return Err(HcError::of("E0203", &[&fname]).focus(fname));
+    }
+    Err(HcError::of("E0201", &[&expr]).focus(expr))
+}
+
+// 需要普通值的地方（运算、下标、字段、方法调用）不接受 none
+fn require_value(val: String, expr: &str) -> Result<String, HcError> {
+    if val == "none" {
+        return Err(HcError::of("E0123", &[&expr.trim()]));
+    }
+    Ok(val)
+}
+
+// 求值逗号分隔的参数列表
+fn eval_args(args: &str, prog: &Program, rt: &mut Runtime) -> Result<Vec<String>, HcError> {
+    split_top_level(args, ',').into_iter()
+        .filter(|s| !s.is_empty())
+        .map(|arg| eval_value(&arg, prog, rt))
+        .collect()
+}
+
+// 按类型把值存入对应的表：列表/字典单独存放，其余存为变量
+// 登记一个声明；同一作用域里已在别的行声明过时报错（循环中重复执行同一行声明不算）
+fn declare(decls: &mut HashMap<String, usize>, name: &str, line: usize) -> Result<(), HcError> {
+    match decls.get(name) {
+        Some(&0) => Err(HcError::of("E0209", &[&name]).focus(name)),
+        Some(&prev) if prev != line => Err(HcError::of("E0210", &[&name, &prev]).focus(name)),
+        _ => {
+            decls.insert(name.to_string(), line);
+            Ok(())
+        }
+    }
+}
+
+// 赋值或修改的目标（如 xs[0]、p.x）的根名字是常量时报错
+fn check_not_const(target: &str, is_const: impl Fn(&str) -> bool) -> Result<(), HcError> {
+    let root = target.split(['[', '.']).next().unwrap_or(target).trim();
+    if is_const(root) {
+        return Err(HcError::of("E0130", &[&root]).focus(root));
+    }
+    Ok(())
+}
+
+// 函数参数可以与调用方的常量同名，此时名字指的是参数
+fn is_runtime_const(rt: &Runtime, name: &str) -> bool {
+    rt.constants.contains_key(name) && !rt.var_types.contains_key(name)
+}
+
+// 常量初始值在编译期求值：只能使用字面量、其他常量和运算，不能使用变量、函数调用和输入
+fn eval_const(name: &str, expr: &str, prog: &Program, constants: &HashMap<String, String>) -> Result<String, HcError> {
+    let not_const = || HcError::of("E0011", &[&name, &expr.trim()]).focus(expr.trim());
+    let code: String = code_chars(expr).into_iter().map(|(_, c)| c).collect();
+    // 不带 call 的调用：( 前面紧挨着的名字是函数名
+    let calls_function = code.match_indices('(').any(|(idx, _)| {
+        let before = code[..idx].trim_end();
+        prog.functions.contains_key(&before[before.trim_end_matches(is_word_char).len()..])
+    });
+    if calls_function || ["call ", "input(", "trace()"].iter().any(|k| code.contains(k)) {
+        return Err(not_const());
+    }
+    let mut rt = Runtime { constants: constants.clone(), ..Runtime::default() };
+    eval_value(expr, prog, &mut rt).map_err(|e| if e.kind == ErrorKind::Name { not_const() } else { e })
+}
+
+fn bind_value(rt: &mut Runtime, name: &str, val: String, vtype: VarType) -> Result<(), HcError> {
+    match vtype {
+        VarType::List(_) => {
+            let items = parse_list_value(&val).ok_or_else(|| HcError::of("E0131", &[&name, &val]))?;
+            rt.lists.insert(name.to_string(), items);
+        }
+        VarType::Map(_, _) => {
+            let entries = parse_map_value(&val).ok_or_else(|| HcError::of("E0132", &[&name, &val]))?;
+            rt.maps.insert(name.to_string(), entries);
+        }
+        // 集合可以用集合或列表初始化，重复元素只保留一个
+        VarType::Set(_) => {
+            let items = parse_set_value(&val).or_else(|| parse_list_value(&val))
+                .ok_or_else(|| HcError::of("E0133", &[&name, &val]))?;
+            rt.sets.insert(name.to_string(), parse_set_value(&format_set(&items)).unwrap_or_default());
+        }
+        VarType::Deque(_) => {
+            let items = parse_list_value(&val).ok_or_else(|| HcError::of("E0131", &[&name, &val]))?;
+            rt.deques.insert(name.to_string(), items.into());
+        }
+        _ => { rt.variables.insert(name.to_string(), val); }
+    }
+    rt.var_types.insert(name.to_string(), vtype);
+    Ok(())
+}
+
+// 查找顶层算术运算符：先找最后一个 + -，再找最后一个 * / %（保证左结合与优先级）
+fn find_arith_op(expr: &str) -> Option<(usize, char)> {
+    let positions = top_level_indices(expr);
+    for ops in [&['+', '-'][..], &['*', '/', '%'][..]] {
+        for &idx in positions.iter().rev() {
+            let c = expr[idx..].chars().next().unwrap_or(' ');
+            if !ops.contains(&c) { continue; }
+            // 一元负号或比较运算符的一部分不算二元运算
+            let before = expr[..idx].trim_end();
+            if before.is_empty() || before.ends_with(['+', '-', '*', '/', '%', '=', '<', '>', '!', ',']) { continue; }
+            if expr[idx+1..].starts_with('=') { continue; }
+            return Some((idx, c));
+        }
+    }
+    None
+}
+
+// 调用自定义函数：参数求值后绑定到局部作用域，返回 end(...) 表达式的值
+fn call_function(call_expr: &str, prog: &Program, rt: &mut Runtime) -> Result<String, HcError> {
+    let (fname, args) = split_call(call_expr);
+    let Some(statements) = prog.functions.get(fname) else {
+        return Err(HcError::of("E0203", &[&fname]).focus(fname));
+    };
+    let arg_vals = eval_args(args, prog, rt)?;
+    let sig = prog.function_sigs.get(fname);
+    if let Some((params, _)) = sig && params.len() != arg_vals.len() {
+        return Err(HcError::of("E0107", &[&fname, &params.len(), &arg_vals.len()]).focus(call_expr));
+    }
+    let mut local = rt.clone();
+    let frame_args = sig.map(|(params, _)| params.iter().map(|(_, pname)| pname.clone()).zip(arg_vals.iter().cloned()).collect());
+    local.frames.push(Frame { function: fname.to_string(), call_line: rt.line, args: frame_args.unwrap_or_default() });
+    // 函数体是新的作用域，只有常量仍然不能被重复声明
+    let constants = &local.constants;
+    local.decls.retain(|name, _| constants.contains_key(name));
+    if let Some((params, _ret_type)) = sig {
+        for ((ptype, pname), val) in params.iter().zip(arg_vals) {
+            // 参数会遮蔽调用方的同名变量/列表/字典
+            local.variables.remove(pname);
+            local.lists.remove(pname);
+            local.maps.remove(pname);
+            local.sets.remove(pname);
+            local.deques.remove(pname);
+            let vtype = parse_type(ptype);
+            if val == "none" && !matches!(vtype, VarType::Opt(_)) {
+                return Err(HcError::of("E0102", &[&tr("what.param", &[&fname, &pname])]));
+            }
+            bind_value(&mut local, pname, val, vtype).map_err(|e| e.context(&tr("ctx.param", &[])))?;
+            local.decls.insert(pname.clone(), 0);
+        }
+    }
+    let result = run_function(fname, statements, prog, &mut local);
+    write_back_globals(rt, &local);
+    result
+}
+
+// 函数体对模块级变量的修改写回调用方；参数和函数内同名的局部声明遮蔽了模块级变量，不写回
+fn write_back_globals(rt: &mut Runtime, local: &Runtime) {
+    for name in rt.globals.iter().filter(|name| !local.decls.contains_key(*name)) {
+        if let Some(val) = local.variables.get(name) {
+            rt.variables.insert(name.clone(), val.clone());
+        }
+        if let Some(items) = local.lists.get(name) {
+            rt.lists.insert(name.clone(), items.clone());
+        }
+        if let Some(entries) = local.maps.get(name) {
+            rt.maps.insert(name.clone(), entries.clone());
+        }
+        if let Some(items) = local.sets.get(name) {
+            rt.sets.insert(name.clone(), items.clone());
+        }
+        if let Some(items) = local.deques.get(name) {
+            rt.deques.insert(name.clone(), items.clone());
+        }
+    }
+}
+
+// 执行函数体并计算 end(...) 中的返回值
+fn run_function(fname: &str, statements: &[SourceLine], prog: &Program, local: &mut Runtime) -> Result<String, HcError> {
+    let sig = prog.function_sigs.get(fname);
+    eval_block(statements, prog, local, 0)?;
+    // end() 没有返回值时得到 none
+    let ret = match prog.function_returns.get(fname) {
+        Some(ret) if !ret.is_empty() => ret,
+        _ => return Ok("none".to_string()),
+    };
+    // 求值 end(...) 时出错的位置和 trace() 显示的行号都是 end(...) 所在的行
+    let end_line = prog.function_ends.get(fname);
+    if let Some(end) = end_line {
+        local.line = end.number;
+    }
+    let at_end = |e: HcError, frames: &[Frame]| match end_line {
+        Some(end) => e.at(end, frames),
+        None => e.capture(frames, 0),
+    };
+    // end(a, b) 返回元组
+    let ret_val = if split_top_level(ret, ',').len() > 1 {
+        eval_value(&format!("({})", ret), prog, local)
+    } else {
+        eval_value(ret, prog, local)
+    };
+    let ret_val = ret_val.map_err(|e| at_end(e, &local.frames))?;
+    if let Some((_, Some(ret_type))) = sig {
+        let vtype = parse_type(ret_type);
+        if vtype != VarType::Unknown && !check_type(&ret_val, &vtype) {
+            return Err(at_end(HcError::of("E0106", &[&fname, &ret_type, &ret_val]), &local.frames));
+        }
+    }
+    Ok(ret_val)
+}
+
+// 拆分声明左侧 "类型 名称"，类型可以是多个词（如 list int）
+fn split_decl(type_and_name: &str) -> (&str, &str) {
+    let type_and_name = type_and_name.trim();
+    match type_and_name.rsplit_once(char::is_whitespace) {
+        Some((typ, name)) => (typ.trim(), name),
+        None => ("", type_and_name),
+    }
+}
+
+// 整个表达式是否就是一次 input(...) 调用（后面接方法调用时不算）
+fn is_input_call(expr: &str) -> bool {
+    expr.strip_prefix("input(").and_then(|rest| rest.strip_suffix(')')).is_some_and(is_balanced)
+}
+
+// 读取一行输入 input("xxx")，结果是字符串
+// 声明为 int/double/bool 时把输入转换为对应类型，转换失败是 ValueError
+fn read_input(val: &str, typ: &str) -> Result<String, HcError> {
+    let prompt = match (val.find('"'), val.rfind('"')) {
+        (Some(start), Some(end)) if start < end => display_value(&val[start..=end]),
+        _ => String::new(),
+    };
+    let mut out = io::stdout().lock();
+    write!(out, "{}", prompt).and_then(|_| out.flush()).map_err(|e| HcError::of("E0704", &[&e]))?;
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) => return Err(HcError::of("E0705", &[&tr("what.eof", &[])])),
+        Ok(_) => {}
+        Err(e) => return Err(HcError::of("E0705", &[&e])),
+    }
+    let input = input.trim_end_matches(['\r','\n']);
+    let vtype = parse_type(typ);
+    if vtype == VarType::Str {
+        // 存储时加双引号
+        return Ok(quote_str(input));
+    }
+    let input = input.trim();
+    if check_type(input, &vtype) {
+        Ok(input.to_string())
+    } else {
+        Err(HcError::of("E0302", &[&input, &typ]))
+    }
+}
+
+// 递归执行代码块，基于缩进；语句出错时立即停止，错误交给外层的 catch 或由 main 报告
+fn eval_block(block: &[SourceLine], prog: &Program, rt: &mut Runtime, parent_indent: usize) -> Result<(), HcError> {
+    // 子块执行完后恢复外层语句的行号
+    let outer_line = rt.line;
+    let mut i = 0;
+    while i < block.len() {
+        let line = &block[i];
+        if line.trim().is_empty() { i += 1; continue; }
+        // 块外，直接返回
+        if indent_of(line) < parent_indent { break; }
+        // 默认下一条语句紧跟其后，带子块的语句会把它移到子块之后
+        let mut next = i + 1;
+        rt.line = line.number;
+        eval_statement(block, i, &mut next, prog, rt).map_err(|e| e.at(line, &rt.frames))?;
+        i = next;
+    }
+    rt.line = outer_line;
+    Ok(())
+}
+
+// 执行第 i 行的语句，next 设置为下一条语句的位置
+fn eval_statement(block: &[SourceLine], i: usize, next: &mut usize, prog: &Program, rt: &mut Runtime) -> Result<(), HcError> {
+    let line = &block[i];
+    let cmd = line.trim();
+    let indent = indent_of(line);
+    // 变量声明，初始值可以是任意表达式（包括函数调用和列表）
+    if let Some(rest) = cmd.strip_prefix("var ") {
+        // 解构元组：var (int q, int r) = call divmod(7, 2)
+        if let Some((targets, value)) = rest.split_once('=') && targets.trim().starts_with('(') && targets.trim().ends_with(')') {
+            let targets = parse_params(targets.trim().trim_start_matches('(').trim_end_matches(')'));
+            let vtypes: Vec<VarType> = targets.iter().map(|(t, _)| parse_type(t)).collect();
+            if targets.iter().zip(&vtypes).any(|((_, name), t)| name.is_empty() || *t == VarType::Unknown || !type_defined(t, prog)) {
+                return Err(HcError::of("E0002", &[&cmd]));
+            }
+            for (_, name) in &targets {
+                declare(&mut rt.decls, name, rt.line)?;
+            }
+            let v = eval_value(value, prog, rt).map_err(|e| e.within(tr("ctx.destructure", &[])))?;
+            let items = match parse_tuple_value(&v) {
+                Some(items) if items.len() == targets.len() => items,
+                _ => return Err(HcError::of("E0105", &[&v, &targets.len()])),
+            };
+            for (((_, name), vtype), item) in targets.iter().zip(vtypes).zip(items) {
+                if !check_type(&item, &vtype) {
+                    return Err(HcError::of("E0101", &[&tr("what.var", &[&name]), &item]).focus(name));
+                }
+                rt.variables.insert(name.clone(), item);
+                rt.var_types.insert(name.clone(), vtype);
+            }
+            return Ok(());
+        }
+        let Some((type_and_name, value)) = rest.split_once('=') else {
+            return Err(HcError::of("E0002", &[&cmd]));
+        };
+        let (typ, name) = split_decl(type_and_name);
+        let val = value.trim();
+        let context = tr("ctx.var_assign", &[&name]);
+        // 省略类型（var x = 5）时由初始值推断
+        if typ.is_empty() {
+            if !is_identifier(name) {
+                return Err(HcError::of("E0002", &[&cmd]));
+            }
+            declare(&mut rt.decls, name, rt.line)?;
+            let v = eval_value(val, prog, rt).map_err(|e| e.within(context))?;
+            let vtype = infer_decl_type(val, &v, prog);
+            if vtype == VarType::Unknown || vtype == none_type() {
+                return Err(HcError::of("E0104", &[&name, &v]).focus(val));
+            }
+            if !check_type(&v, &vtype) {
+                return Err(HcError::of("E0101", &[&tr("what.var", &[&name]), &v]).focus(val));
+            }
+            rt.variables.insert(name.to_string(), v);
+            rt.var_types.insert(name.to_string(), vtype);
+            return Ok(());
+        }
+        let vtype = parse_type(typ);
+        if vtype == VarType::Unknown || !type_defined(&vtype, prog) || name.is_empty() {
+            return Err(HcError::of("E0002", &[&cmd]));
+        }
+        declare(&mut rt.decls, name, rt.line)?;
+        let v = if matches!(vtype, VarType::Int | VarType::Double | VarType::Str | VarType::Bool)
+            && is_input_call(val)
+        {
+            read_input(val, typ)
+        } else {
+            eval_value(val, prog, rt)
+        };
+        match v.map_err(|e| e.within(context))? {
+            // 类型检查
+            v if check_type(&v, &vtype) => {
+                rt.variables.insert(name.to_string(), v);
+                rt.var_types.insert(name.to_string(), vtype);
+            }
+            v if v == "none" => return Err(HcError::of("E0102", &[&tr("what.var", &[&name])]).focus(val)),
+            v => return Err(HcError::of("E0101", &[&tr("what.var", &[&name]), &v]).focus(val)),
+        }
+    // 常量声明
+    } else if let Some(rest) = cmd.strip_prefix("const ") {
+        let Some((type_and_name, value)) = rest.split_once('=') else {
+            return Err(HcError::of("E0003", &[&cmd]));
+        };
+        let (typ, name) = split_decl(type_and_name);
+        let vtype = parse_type(typ);
+        if vtype == VarType::Unknown || !type_defined(&vtype, prog) || name.is_empty() {
+            return Err(HcError::of("E0003", &[&cmd]));
+        }
+        declare(&mut rt.decls, name, rt.line)?;
+        rt.const_types.insert(name.to_string(), vtype.clone());
+        let v = eval_const(name, value, prog, &rt.constants).map_err(|e| e.within(tr("ctx.const_assign", &[&name])))?;
+        if !check_type(&v, &vtype) {
+            return Err(HcError::of("E0101", &[&tr("what.const", &[&name]), &v]).focus(value.trim()));
+        }
+        rt.constants.insert(name.to_string(), v);
+    // 支持 list 声明带类型，如 list int a = [1, 2, 3]、list list int m = [[1, 2], [3]]
+    // 以及 map str int scores = {"a": 1}、set int s = {1, 2}、deque int q = [1, 2]
+    } else if let Some((kind, label, empty, rest)) = COLLECTION_KINDS.iter()
+        .find_map(|&(kind, label, empty)| cmd.strip_prefix(kind).and_then(|r| r.strip_prefix(' ')).map(|r| (kind, label, empty, r)))
+    {
+        let label = tr(label, &[]);
+        let (type_and_name, value) = if let Some((type_and_name, value)) = rest.split_once('=') {
+            (type_and_name.trim(), value.trim())
+        } else {
+            (rest.trim(), "")
+        };
+        let (typ, name) = split_decl(type_and_name); // 兼容无类型写法
+        let vtype = parse_type(&format!("{} {}", kind, typ));
+        if !type_defined(&vtype, prog) {
+            return Err(HcError::of("E0004", &[&label, &cmd]));
+        }
+        declare(&mut rt.decls, name, rt.line)?;
+        let value = if value.is_empty() { empty } else { value };
+        let v = eval_value(value, prog, rt).map_err(|e| e.within(tr("ctx.collection_assign", &[&label, &name])))?;
+        if !check_type(&v, &vtype) {
+            return Err(HcError::of("E0103", &[&format!("{} {}", label, name), &v]).focus(value));
+        }
+        // 省略元素类型时由初始值推断
+        let vtype = refine_type(vtype, infer_decl_type(value, &v, prog));
+        bind_value(rt, name, v, vtype).map_err(|e| e.within(label.clone()))?;
+    } else if let Some(expr) = cmd.strip_prefix("say ") {
+        let expr = expr.trim();
+        match eval_value(expr, prog, rt) {
+            Ok(val) => write_line(&display_value(&val))?,
+            Err(_) if is_identifier(expr) => {
+                return Err(HcError::of("E0205", &[&expr]).focus(expr));
+            }
+            Err(e) => return Err(e.within(tr("ctx.say", &[]))),
+        }
+    // 抛出错误：throw "消息" 或 throw Error(kind: "...", message: "...")
+    } else if let Some(expr) = cmd.strip_prefix("throw ") {
+        let v = eval_value(expr, prog, rt)?;
+        if is_string_literal(&v) {
+            return Err(HcError::new(ErrorKind::Thrown("Error".to_string()), display_value(&v)));
+        }
+        return match parse_struct_value(&v) {
+            Some(("Error", fields)) => {
+                let field = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| display_value(v)).unwrap_or_default();
+                Err(HcError::new(ErrorKind::from_name(&field("kind")), field("message")))
+            }
+            _ => Err(HcError::of("E0134", &[&v]).focus(expr.trim())),
+        };
+    // exit(n) 立即以退出码 n 结束程序，exit() 等同于 exit(0)
+    } else if cmd.starts_with("exit(") && cmd.ends_with(')') {
+        let (_, args) = split_call(cmd);
+        let code = if args.trim().is_empty() { "0".to_string() } else { eval_value(args, prog, rt)? };
+        let Ok(code) = code.parse::<i32>() else {
+            return Err(HcError::of("E0135", &[&code]).focus(args.trim()));
+        };
+        io::stdout().flush().map_err(|e| HcError::of("E0704", &[&e]))?;
+        process::exit(code);
+    // 语句形式的方法调用，如 scores.remove("a")、q.push_back(1)，返回值被丢弃
+    } else if split_method_call(cmd).is_some_and(|(base, _, _)| is_identifier(base)) {
+        eval_value(cmd, prog, rt)?;
+    // 语句形式的函数调用 f(x)，返回值被丢弃
+    } else if call_name(cmd).is_some() {
+        eval_value(cmd, prog, rt)?;
+    } else if (cmd.contains("+=") || cmd.contains("-=") || cmd.contains("*=") || cmd.contains("/=") || cmd.contains("%=")) && !cmd.starts_with("call ") {
+        let op = if cmd.contains("+=") { "+=" } else if cmd.contains("-=") { "-=" } else if cmd.contains("*=") { "*=" } else if cmd.contains("/=") { "/=" } else { "%=" };
+        if let Some((name, value)) = cmd.split_once(op) {
+            // 目标可以是变量，也可以是下标访问，如 scores["a"] += 1
+            let name = name.trim();
+            let context = tr("ctx.var_assign", &[&name]);
+            let old_val = match eval_value(name, prog, rt) {
+                Ok(old_val) => old_val,
+                Err(_) if is_identifier(name) => {
+                    return Err(HcError::of("E0202", &[&name]).focus(name));
+                }
+                Err(e) => return Err(e.within(context)),
+            };
+            if old_val == "none" {
+                return Err(HcError::of("E0124", &[&name, &op]).focus(name));
+            }
+            let left = old_val.parse::<f64>().unwrap_or(0.0);
+            let Ok(right) = eval_value(value, prog, rt).map_err(|e| e.within(context))?.parse::<f64>() else {
+                return Err(HcError::of("E0122", &[&name, &value.trim()]).focus(value.trim()));
+            };
+            if right == 0.0 && matches!(op, "/=" | "%=") {
+                return Err(HcError::of("E0601", &[&cmd]));
+            }
+            let result = match op {
+                "+=" => left + right,
+                "-=" => left - right,
+                "*=" => left * right,
+                "/=" => left / right,
+                "%=" => left % right,
+                _ => left,
+            };
+            assign(name, result.to_string(), prog, rt)?;
+        }
+    } else if cmd.contains('=') && !cmd.starts_with("call ") && !cmd.starts_with("if ") && !cmd.starts_with("while ") && !cmd.starts_with("for ") && !cmd.starts_with("do ") && !cmd.starts_with("switch ") {
+        if let Some((target, value)) = cmd.split_once('=') {
+            let target = target.trim();
+            let val = eval_value(value, prog, rt).map_err(|e| e.within(tr("ctx.var_assign", &[&target])))?;
+            assign(target, val, prog, rt)?;
+        }
+    } else if cmd.starts_with("call ") {
+        eval_value(cmd, prog, rt)?;
+    } else if cmd.starts_with("if ") && cmd.ends_with(':') {
+        let cond = cmd[3..cmd.len()-1].trim();
+        let (inner_block, j) = collect_block(block, i, indent);
+        *next = j;
+        if eval_condition(cond, prog, rt)? {
+            // if x is not none: 块内 x 的类型收窄为非可选类型
+            let narrowed = narrow_opt(cond, rt);
+            let result = eval_block(&inner_block, prog, rt, indent+1);
+            if let Some((name, vtype)) = narrowed {
+                rt.var_types.insert(name, vtype);
+            }
+            result?;
+        }
+    } else if cmd.starts_with("while ") && cmd.ends_with(':') {
+        let cond = cmd[6..cmd.len()-1].trim();
+        let (inner_block, j) = collect_block(block, i, indent);
+        *next = j;
+        while eval_condition(cond, prog, rt)? {
+            eval_block(&inner_block, prog, rt, indent+1)?;
+        }
+    } else if cmd.starts_with("for ") && cmd.ends_with(':') {
+        let cond = cmd[4..cmd.len()-1].trim();
+        let (inner_block, j) = collect_block(block, i, indent);
+        *next = j;
+        let Some((vars, rest)) = cond.split_once(" in ") else {
+            return Err(HcError::of("E0006", &[&cmd]));
+        };
+        let vars: Vec<&str> = vars.split(',').map(|v| v.trim()).collect();
+        for var in &vars {
+            check_not_const(var, |name| is_runtime_const(rt, name))?;
+        }
+        // 可以遍历列表/集合/双端队列或任意结果为列表的表达式；字典用 for k, v in m 遍历键值对
+        let v = eval_value(rest, prog, rt).map_err(|e| e.within(tr("ctx.for", &[])))?;
+        let entries = if let Some(items) = parse_list_value(&v) {
+            items.into_iter().map(|item| (item, None)).collect::<Vec<_>>()
+        } else if let Some(entries) = parse_map_value(&v) {
+            entries.into_iter().map(|(k, v)| (k, Some(v))).collect()
+        } else if let Some(items) = parse_set_value(&v) {
+            items.into_iter().map(|item| (item, None)).collect()
+        } else {
+            return Err(HcError::of("E0136", &[&rest.trim()]).focus(rest.trim()));
+        };
+        match vars.as_slice() {
+            [var] => {
+                for (item, _) in entries {
+                    rt.variables.insert(var.to_string(), item);
+                    eval_block(&inner_block, prog, rt, indent+1)?;
+                }
+            }
+            [key_var, value_var] if entries.iter().all(|(_, v)| v.is_some()) => {
+                for (key, value) in entries {
+                    rt.variables.insert(key_var.to_string(), key);
+                    rt.variables.insert(value_var.to_string(), value.unwrap_or_default());
+                    eval_block(&inner_block, prog, rt, indent+1)?;
+                }
+            }
+            _ => return Err(HcError::of("E0006", &[&cmd])),
+        }
+    } else if cmd.starts_with("do ") && cmd.ends_with(':') {
+        let cond = cmd[3..cmd.len()-1].trim();
+        let (inner_block, j) = collect_block(block, i, indent);
+        *next = j;
+        loop {
+            eval_block(&inner_block, prog, rt, indent+1)?;
+            if !eval_condition(cond, prog, rt)? { break; }
+        }
+    } else if cmd.starts_with("switch ") && cmd.ends_with(':') {
+        let subject = cmd[7..cmd.len()-1].trim();
+        let (cases, default_block, j) = collect_cases(block, i, indent);
+        *next = j;
+        let val = eval_value(subject, prog, rt).map_err(|e| e.within(tr("ctx.switch", &[])))?;
+        let mut arm = None;
+        for (label, _, body) in &cases {
+            if let Some(binds) = match_case(label, &val, prog, rt).map_err(|e| e.within(tr("ctx.case", &[&label])))? {
+                arm = Some((binds, body));
+                break;
+            }
+        }
+        if let Some((binds, body)) = arm {
+            // 绑定枚举载荷和守卫中的名字
+            for (name, v) in binds {
+                check_not_const(&name, |n| is_runtime_const(rt, n))?;
+                rt.variables.insert(name, v);
+            }
+            eval_block(body, prog, rt, indent+1)?;
+        } else if let Some(default_block) = default_block {
+            eval_block(&default_block, prog, rt, indent+1)?;
+        }
+    // try: 块中的错误交给紧随其后的 catch 名字: 处理，finally: 块总会执行
+    } else if cmd == "try:" {
+        let (try_block, mut j) = collect_block(block, i, indent);
+        let clause = |j: usize| block.get(j).filter(|l| indent_of(l) == indent).map(|l| l.trim());
+        let mut catch = None;
+        if let Some(name) = clause(j).and_then(catch_name) {
+            check_not_const(name, |n| is_runtime_const(rt, n))?;
+            let (catch_block, after) = collect_block(block, j, indent);
+            catch = Some((name.to_string(), catch_block));
+            j = after;
+        }
+        let mut finally = None;
+        if clause(j) == Some("finally:") {
+            let (finally_block, after) = collect_block(block, j, indent);
+            finally = Some(finally_block);
+            j = after;
+        }
+        *next = j;
+        if catch.is_none() && finally.is_none() {
+            return Err(HcError::of("E0007", &[]));
+        }
+        let result = eval_block(&try_block, prog, rt, indent+1);
+        let result = match (result, catch) {
+            (Err(e), Some((name, catch_block))) => {
+                if !name.is_empty() {
+                    rt.variables.insert(name.clone(), e.to_value());
+                    rt.var_types.insert(name, VarType::Named("Error".to_string()));
+                }
+                eval_block(&catch_block, prog, rt, indent+1)
+            }
+            (result, _) => result,
+        };
+        if let Some(finally_block) = finally {
+            eval_block(&finally_block, prog, rt, indent+1)?;
+        }
+        // 没有被捕获的错误继续向外报告
+        result?;
+    } else if catch_name(cmd).is_some() || cmd == "finally:" {
+        *next = collect_block(block, i, indent).1;
+        return Err(HcError::of("E0008", &[&cmd]));
+    }
+    // 检查是否为直接写了自定义函数名而没有加括号（需排除块结构、声明、say等所有已知语法）
+    else if prog.functions.contains_key(cmd) {
+        return Err(HcError::of("E0009", &[&cmd]));
+    } else {
+        return Err(HcError::of("E0029", &[&cmd]));
+    }
+    Ok(())
+}
+
+// 解析 catch: 或 catch 名字:，返回绑定错误值的名字（可以为空）
+fn catch_name(cmd: &str) -> Option<&str> {
+    if cmd == "catch:" {
+        return Some("");
+    }
+    let name = cmd.strip_prefix("catch ")?.strip_suffix(':')?.trim();
+    is_identifier(name).then_some(name)
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// 收集 switch 的分支：返回 [(case 标签, 分支体)]、default 分支和下一条语句的位置
+// 只有紧挨在 switch 下一层的 case/default 才是分支标签，嵌套的 switch 留在分支体里
+fn collect_cases(block: &[SourceLine], i: usize, this_indent: usize) -> (Cases, Option<Vec<SourceLine>>, usize) {
+    let (inner_block, j) = collect_block(block, i, this_indent);
+    let case_indent = inner_block.first().map(|l| indent_of(l)).unwrap_or(0);
+    let mut cases: Cases = Vec::new();
+    let mut default_block: Option<Vec<SourceLine>> = None;
+    let mut in_default = false;
+    for l in inner_block {
+        let ltrim = l.trim();
+        if indent_of(&l) == case_indent && ltrim.starts_with("case ") && ltrim.ends_with(':') {
+            cases.push((ltrim[5..ltrim.len()-1].trim().to_string(), l.clone(), Vec::new()));
+            in_default = false;
+        } else if indent_of(&l) == case_indent && ltrim == "default:" {
+            default_block = Some(Vec::new());
+            in_default = true;
+        } else if in_default {
+            if let Some(body) = default_block.as_mut() { body.push(l); }
+        } else if let Some((_, _, body)) = cases.last_mut() {
+            body.push(l);
+        }
+    }
+    (cases, default_block, j)
+}
+
+// 判断 case 标签是否匹配，匹配时返回需要绑定的变量（枚举载荷或守卫中的名字）
+// 标签可以是值列表 1, 2, 3、范围 10..20（不含 20）/ 10..=20、枚举成员，以及带守卫的 n if n > 100
+fn match_case(label: &str, val: &str, prog: &Program, rt: &mut Runtime) -> Result<Option<Entries>, HcError> {
+    let (pattern, guard) = split_guard(label);
+    for alt in split_top_level(pattern, ',') {
+        let Some(binds) = match_pattern(&alt, val, guard.is_some(), prog, rt)? else { continue };
+        let Some(guard) = guard else { return Ok(Some(binds)) };
+        // 守卫在绑定后的作用域中求值，求值后恢复原来的变量
+        let saved: Vec<(String, Option<String>)> = binds.iter()
+            .map(|(k, v)| (k.clone(), rt.variables.insert(k.clone(), v.clone())))
+            .collect();
+        let ok = eval_condition(guard, prog, rt);
+        for (k, old) in saved {
+            match old {
+                Some(old) => { rt.variables.insert(k, old); }
+                None => { rt.variables.remove(&k); }
+            }
+        }
+        if ok? {
+            return Ok(Some(binds));
+        }
+    }
+    Ok(None)
+}
+
+// 拆出 case 标签中的守卫：pattern if cond
+fn split_guard(label: &str) -> (&str, Option<&str>) {
+    match top_level_indices(label).into_iter().find(|&idx| label[idx..].starts_with(" if ")) {
+        Some(idx) => (label[..idx].trim(), Some(label[idx+4..].trim())),
+        None => (label.trim(), None),
+    }
+}
+
+// 拆出范围 lo..hi 或 lo..=hi，返回 (下界, 上界, 是否包含上界)
+fn split_range(pattern: &str) -> Option<(&str, &str, bool)> {
+    let idx = top_level_indices(pattern).into_iter().find(|&idx| pattern[idx..].starts_with(".."))?;
+    let hi = &pattern[idx+2..];
+    match hi.strip_prefix('=') {
+        Some(hi) => Some((&pattern[..idx], hi, true)),
+        None => Some((&pattern[..idx], hi, false)),
+    }
+}
+
+// 匹配单个候选标签；can_bind 为真（带守卫）时，未定义的名字会绑定为被匹配的值
+fn match_pattern(pattern: &str, val: &str, can_bind: bool, prog: &Program, rt: &mut Runtime) -> Result<Option<Entries>, HcError> {
+    let pattern = pattern.trim();
+    if pattern == "_" {
+        return Ok(Some(Vec::new()));
+    }
+    // 枚举：case Red / case Color.Red / case Circle(r) / case Shape.Circle(r)
+    if let Some((enum_name, variant, payload)) = parse_enum_value(val) {
+        let (lname, binds) = split_call(pattern);
+        let lname = lname.strip_prefix(enum_name).and_then(|l| l.strip_prefix('.')).unwrap_or(lname);
+        if lname != variant {
+            return Ok(None);
+        }
+        let binds = binds.split(',').map(|b| b.trim()).filter(|b| !b.is_empty());
+        return Ok(Some(binds.zip(payload)
+            .filter(|(name, _)| *name != "_")
+            .map(|(name, (_, v))| (name.to_string(), v))
+            .collect()));
+    }
+    if can_bind && is_identifier(pattern) && !rt.variables.contains_key(pattern) && !rt.constants.contains_key(pattern) {
+        return Ok(Some(vec![(pattern.to_string(), val.to_string())]));
+    }
+    if let Some((lo, hi, inclusive)) = split_range(pattern) {
+        let (lo, hi) = (eval_value(lo, prog, rt)?, eval_value(hi, prog, rt)?);
+        let (Ok(lo), Ok(hi)) = (lo.parse::<f64>(), hi.parse::<f64>()) else {
+            return Err(HcError::of("E0137", &[&pattern]));
+        };
+        let Ok(v) = val.parse::<f64>() else { return Ok(None) };
+        return Ok((v >= lo && (v < hi || inclusive && v == hi)).then(Vec::new));
+    }
+    // 按值比较：数字按数值相等，字符串按内容相等
+    let label_val = eval_value(pattern, prog, rt)?;
+    Ok((compare_values(&label_val, val) == std::cmp::Ordering::Equal).then(Vec::new))
+}
+
+// 加载时检查 switch：报告重复的 case 标签；case 引用枚举时，还报告不属于该枚举的标签，以及没有 default 时遗漏的成员
+fn check_switches(prog: &Program, start_commands: &[SourceLine]) {
+    let mut names: Vec<&String> = prog.functions.keys().collect();
+    names.sort();
+    let blocks = names.into_iter().map(|name| prog.functions[name].as_slice()).chain(std::iter::once(start_commands));
+    for block in blocks {
+        for (i, line) in block.iter().enumerate() {
+            let cmd = line.trim();
+            if !(cmd.starts_with("switch ") && cmd.ends_with(':')) { continue; }
+            let (cases, default_block, _) = collect_cases(block, i, indent_of(line));
+            // 每个候选标签：(标签, 是否带守卫, case 所在行)
+            let alternatives: Vec<(String, bool, &SourceLine)> = cases.iter()
+                .flat_map(|(label, case_line, _)| {
+                    let (pattern, guard) = split_guard(label);
+                    split_top_level(pattern, ',').into_iter().map(move |alt| (alt, guard.is_some(), case_line))
+                })
+                .collect();
+            let mut seen: Vec<String> = Vec::new();
+            for (alt, guarded, case_line) in &alternatives {
+                if *guarded { continue; }
+                let key = alt.parse::<f64>().map(|n| n.to_string()).unwrap_or_else(|_| alt.split_whitespace().collect());
+                if seen.contains(&key) {
+                    warn(prog, "W0001", &[&cmd, &alt], case_line, alt);
+                } else {
+                    seen.push(key);
+                }
+            }
+            check_enum_cases(prog, line, &alternatives, default_block.is_some());
+        }
+    }
+}
+
+// 输出加载时的警告
+fn warn(prog: &Program, code: &'static str, args: &[&dyn fmt::Display], line: &SourceLine, focus: &str) {
+    eprintln!("{}", Diagnostic::new(Severity::Warning, code, tr(code, args), &prog.file, line, Some(focus), prog.keywords));
+}
+
+// 检查引用枚举的 case 标签；带守卫的分支不算覆盖了成员
+fn check_enum_cases(prog: &Program, line: &SourceLine, alternatives: &[(String, bool, &SourceLine)], has_default: bool) {
+    let cmd = line.trim();
+    // 标签解析为 (枚举名, 成员名)
+    let resolve = |label: &str| -> Option<(String, String)> {
+        let (lname, _) = split_call(label);
+        if let Some((e, v)) = lname.split_once('.') && prog.enums.contains_key(e) {
+            return Some((e.to_string(), v.to_string()));
+        }
+        let mut owners: Vec<&String> = prog.enums.iter()
+            .filter(|(_, variants)| variants.iter().any(|(v, _)| v == lname))
+            .map(|(e, _)| e)
+            .collect();
+        owners.sort();
+        owners.first().map(|e| (e.to_string(), lname.to_string()))
+    };
+    let Some(enum_name) = alternatives.iter().find_map(|(label, _, _)| resolve(label)).map(|(e, _)| e) else { return };
+    let variants = &prog.enums[&enum_name];
+    let mut covered = Vec::new();
+    for (label, guarded, case_line) in alternatives {
+        if label == "_" {
+            covered.extend(variants.iter().map(|(v, _)| v.as_str()));
+            continue;
+        }
+        let variant = resolve(label).filter(|(e, _)| *e == enum_name)
+            .and_then(|(_, v)| variants.iter().find(|(name, _)| *name == v));
+        match variant {
+            Some((v, fields)) => {
+                let binds = split_call(label).1.split(',').filter(|b| !b.trim().is_empty()).count();
+                if binds > fields.len() {
+                    warn(prog, "W0003", &[&cmd, &label, &binds, &v, &fields.len()], case_line, label);
+                }
+                if !guarded {
+                    covered.push(v.as_str());
+                }
+            }
+            None => warn(prog, "W0002", &[&cmd, &label, &enum_name], case_line, label),
+        }
+    }
+    let missing: Vec<&str> = variants.iter().map(|(v, _)| v.as_str()).filter(|v| !covered.contains(v)).collect();
+    if !has_default && !missing.is_empty() {
+        warn(prog, "W0004", &[&cmd, &enum_name, &missing.join(", ")], line, "");
+    }
+}
+
+// 赋值：支持普通变量、整个列表/字典以及下标赋值 m[0][1] = 5、scores["a"] = 1
+fn assign(target: &str, val: String, prog: &Program, rt: &mut Runtime) -> Result<(), HcError> {
+    check_not_const(target, |name| is_runtime_const(rt, name))?;
+    if rt.variables.contains_key(target) {
+        // 按声明（或推断出）的类型检查新值
+        if let Some(vtype) = rt.var_types.get(target) && !check_type(&val, vtype) {
+            if val == "none" {
+                return Err(HcError::of("E0102", &[&tr("what.var", &[&target])]));
+            }
+            return Err(HcError::of("E0101", &[&tr("what.var", &[&target]), &val]));
+        }
+        rt.variables.insert(target.to_string(), val);
+        return Ok(());
+    }
+    if rt.lists.contains_key(target) || rt.maps.contains_key(target) || rt.sets.contains_key(target) || rt.deques.contains_key(target) {
+        let vtype = rt.var_types.get(target).cloned().unwrap_or(VarType::Unknown);
+        if !check_type(&val, &vtype) {
+            return Err(HcError::of("E0101", &[&tr("what.var", &[&target]), &val]));
+        }
+        return bind_value(rt, target, val, vtype).map_err(|e| e.context(&tr("ctx.var", &[])));
+    }
+    // 下标/字段赋值：从外到内收集路径，如 m[0][1]、p.x、line.a.x
+    let mut path = Vec::new();
+    let mut base = target;
+    loop {
+        if let Some((b, idx)) = split_index_suffix(base) {
+            path.insert(0, eval_value(idx, prog, rt)?);
+            base = b;
+        } else if let Some((b, field)) = split_field_access(base) {
+            path.insert(0, field.to_string());
+            base = b;
+        } else {
+            break;
+        }
+    }
+    if path.is_empty() {
+        return Err(HcError::of("E0202", &[&target]));
+    }
+    // 按声明类型检查键和新值
+    if let Some(vtype) = rt.var_types.get(base) && !check_type(&val, &path_type(vtype, &path, prog)?) {
+        return Err(HcError::of("E0101", &[&tr("what.var", &[&target]), &val]));
+    }
+    if let Some(items) = rt.lists.get_mut(base) {
+        set_path(&format_list(items), &path, val).map(|updated| {
+            *items = parse_list_value(&updated).unwrap_or_default();
+        })
+    } else if let Some(entries) = rt.maps.get_mut(base) {
+        set_path(&format_map(entries), &path, val).map(|updated| {
+            *entries = parse_map_value(&updated).unwrap_or_default();
+        })
+    } else if let Some(old) = rt.variables.get_mut(base) {
+        set_path(old, &path, val).map(|updated| *old = updated)
+    } else {
+        Err(HcError::of("E0204", &[&base]))
+    }
+}
+
+// 比较两个值：都是数字时按数值比较，否则按字符串比较
+fn compare_values(l: &str, r: &str) -> std::cmp::Ordering {
+    match (l.parse::<f64>(), r.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal),
+        _ => l.cmp(r),
+    }
+}
+
+// 条件为 x is not none 且 x 是 opt 类型时，把 x 的类型收窄为内部类型，返回原类型以便块结束后恢复
+fn narrow_opt(cond: &str, rt: &mut Runtime) -> Option<(String, VarType)> {
+    let name = cond.strip_suffix(" is not none")?.trim();
+    let Some(VarType::Opt(inner)) = rt.var_types.get(name).cloned() else { return None };
+    let original = rt.var_types.insert(name.to_string(), *inner)?;
+    Some((name.to_string(), original))
+}
+
+fn eval_condition(cond: &str, prog: &Program, rt: &mut Runtime) -> Result<bool, HcError> {
+    use std::cmp::Ordering;
+    // x is none / x is not none
+    for (suffix, expect_none) in [(" is not none", false), (" is none", true)] {
+        if let Some(subject) = cond.strip_suffix(suffix) {
+            let v = eval_value(subject, prog, rt).map_err(|e| e.within(tr("ctx.condition", &[])))?;
+            return Ok((v == "none") == expect_none);
+        }
+    }
+    let ops = [">=", "<=", "==", "!=", ">", "<"];
+    for idx in top_level_indices(cond) {
+        let Some(op) = ops.iter().find(|op| cond[idx..].starts_with(**op)) else { continue };
+        let l = eval_value(&cond[..idx], prog, rt).map_err(|e| e.within(tr("ctx.condition", &[])))?;
+        let r = eval_value(&cond[idx+op.len()..], prog, rt).map_err(|e| e.within(tr("ctx.condition", &[])))?;
+        // none 只能用 == / != 比较
+        if (l == "none" || r == "none") && !matches!(*op, "==" | "!=") {
+            return Err(HcError::of("E0128", &[&op, &cond]).focus(cond));
+        }
+        let ord = compare_values(&l, &r);
+        return Ok(match *op {
+            ">=" => ord != Ordering::Less,
+            "<=" => ord != Ordering::Greater,
+            "==" => ord == Ordering::Equal,
+            "!=" => ord != Ordering::Equal,
+            ">" => ord == Ordering::Greater,
+            _ => ord == Ordering::Less,
+        });
+    }
+    let v = eval_value(cond, prog, rt).map_err(|e| e.within(tr("ctx.condition", &[])))?;
+    Ok(v == "true")
+}
+
+// 静态检查的作用域
+#[derive(Clone, Default)]
+struct Scope {
+    // 名字 -> 声明类型
+    types: HashMap<String, VarType>,
+    // 常量名 -> 编译期求出的值
+    consts: HashMap<String, String>,
+    // 名字 -> 声明所在行，用于发现重复声明
+    decls: HashMap<String, usize>,
+}
+
+// hitcode doc：按定义顺序列出函数签名，签名下面缩进显示 ## 文档注释
+fn print_docs(prog: &Program) -> Result<(), HcError> {
+    let mut names: Vec<&String> = prog.function_sigs.keys().collect();
+    names.sort_by_key(|name| prog.function_ends.get(*name).map_or(0, |end| end.number));
+    if names.is_empty() {
+        write_line(&tr("ui.doc_none", &[&prog.file]))?;
+    }
+    for (i, name) in names.into_iter().enumerate() {
+        let (params, ret) = &prog.function_sigs[name];
+        let params: Vec<String> = params.iter().map(|(t, n)| format!("{} {}", t, n).trim().to_string()).collect();
+        let ret = ret.as_ref().map(|r| format!("->return::{}", r)).unwrap_or_default();
+        if i > 0 { write_line("")?; }
+        write_line(&prog.keywords.render(&format!("function {}({}){}", name, params.join(", "), ret)))?;
+        for line in prog.function_docs.get(name).into_iter().flat_map(|doc| doc.lines()) {
+            if line.is_empty() { write_line("")?; } else { write_line(&format!("    {}", line))?; }
+        }
+    }
+    Ok(())
+}
+
+// 静态类型检查（hitcode check）：不执行程序，检查所有函数和 start 块，返回全部错误
+fn check_program(prog: &Program, start_commands: &[SourceLine]) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    // 模块级声明对函数和 start 块都可见；函数体是新的作用域，只有常量不能被重复声明
+    let mut globals = Scope::default();
+    check_block(&prog.globals, prog, &mut globals, &mut diags);
+    let mut function_scope = globals.clone();
+    function_scope.decls.retain(|name, _| globals.consts.contains_key(name));
+    let mut names: Vec<&String> = prog.functions.keys().collect();
+    names.sort();
+    for name in names {
+        let mut scope = function_scope.clone();
+        let (params, ret_type) = &prog.function_sigs[name];
+        for (ptype, pname) in params {
+            scope.types.insert(pname.clone(), parse_type(ptype));
+            scope.decls.insert(pname.clone(), 0);
+        }
+        check_block(&prog.functions[name], prog, &mut scope, &mut diags);
+        // end(...) 的值按 ->return:: 类型检查
+        let (Some(ret_type), Some(end_line)) = (ret_type, prog.function_ends.get(name)) else { continue };
+        let vtype = parse_type(ret_type);
+        if vtype == VarType::Unknown { continue; }
+        let ret = prog.function_returns.get(name).map(|r| r.as_str()).unwrap_or("");
+        let ret_expr = if split_top_level(ret, ',').len() > 1 { format!("({})", ret) } else { ret.to_string() };
+        let actual = if ret.is_empty() { Ok(none_type()) } else { infer_type(&ret_expr, prog, &scope) };
+        let result = actual.and_then(|actual| {
+            if assignable(&actual, &vtype) { return Ok(()); }
+            Err(HcError::of("E0106", &[&name, &ret_type, &if ret.is_empty() { "none" } else { ret }]).focus(ret))
+        });
+        if let Err(e) = result {
+            diags.push(check_diagnostic(prog, end_line, e));
+        }
+    }
+    check_block(start_commands, prog, &mut globals, &mut diags);
+    diags
+}
+
+fn check_diagnostic(prog: &Program, line: &SourceLine, err: HcError) -> Diagnostic {
+    Diagnostic::new(Severity::Error, err.code, err.full_message(), &prog.file, line, err.focus.as_deref(), prog.keywords)
+}
+
+// none 字面量的类型
+fn none_type() -> VarType {
+    VarType::Opt(Box::new(VarType::Unknown))
+}
+
+// 检查代码块；块内声明的名字在块结束后仍然可见（与运行时一致）
+fn check_block(block: &[SourceLine], prog: &Program, scope: &mut Scope, diags: &mut Vec<Diagnostic>) {
+    let mut i = 0;
+    while i < block.len() {
+        let line = &block[i];
+        if line.trim().is_empty() { i += 1; continue; }
+        let mut next = i + 1;
+        if let Err(e) = check_statement(block, i, &mut next, prog, scope, diags) {
+            diags.push(check_diagnostic(prog, line, e));
+        }
+        i = next;
+    }
+}
+
+// 检查第 i 行的语句，对应 eval_statement 的各个分支；next 设置为下一条语句的位置
+fn check_statement(block: &[SourceLine], i: usize, next: &mut usize, prog: &Program, scope: &mut Scope, diags: &mut Vec<Diagnostic>) -> Result<(), HcError> {
+    let line = &block[i];
+    let cmd = line.trim();
+    let indent = indent_of(line);
+    // 带子块的语句：先确定子块范围，出错时也跳过整个子块
+    let body = if cmd.ends_with(':') && cmd != "try:" && !cmd.starts_with("switch ") {
+        let (inner, j) = collect_block(block, i, indent);
+        *next = j;
+        inner
+    } else {
+        Vec::new()
+    };
+    if let Some(rest) = cmd.strip_prefix("var ") {
+        let (targets, value) = rest.split_once('=')
+            .ok_or_else(|| HcError::of("E0002", &[&cmd]))?;
+        if targets.trim().starts_with('(') && targets.trim().ends_with(')') {
+            let targets = parse_params(targets.trim().trim_start_matches('(').trim_end_matches(')'));
+            let vtypes: Vec<VarType> = targets.iter().map(|(t, _)| parse_type(t)).collect();
+            if targets.iter().zip(&vtypes).any(|((_, name), t)| name.is_empty() || *t == VarType::Unknown || !type_defined(t, prog)) {
+                return Err(HcError::of("E0002", &[&cmd]));
+            }
+            for ((_, name), vtype) in targets.iter().zip(&vtypes) {
+                declare(&mut scope.decls, name, line.number)?;
+                scope.types.insert(name.clone(), vtype.clone());
+            }
+            return expect_type(value, &VarType::Tuple(vtypes), prog, scope, &tr("what.destructure", &[]));
+        }
+        let (typ, name) = split_decl(targets);
+        let val = value.trim();
+        if typ.is_empty() && is_identifier(name) {
+            declare(&mut scope.decls, name, line.number)?;
+            let vtype = infer_type(val, prog, scope)?;
+            scope.types.insert(name.to_string(), vtype.clone());
+            if vtype == none_type() {
+                return Err(HcError::of("E0104", &[&name, &"none"]).focus(val));
+            }
+            return Ok(());
+        }
+        let vtype = parse_type(typ);
+        if vtype == VarType::Unknown || !type_defined(&vtype, prog) || name.is_empty() {
+            return Err(HcError::of("E0002", &[&cmd]));
+        }
+        declare(&mut scope.decls, name, line.number)?;
+        scope.types.insert(name.to_string(), vtype.clone());
+        if is_input_call(val) && matches!(vtype, VarType::Int | VarType::Double | VarType::Str | VarType::Bool) {
+            return Ok(());
+        }
+        return expect_type(val, &vtype, prog, scope, &tr("what.var", &[&name]));
+    }
+    if let Some(rest) = cmd.strip_prefix("const ") {
+        let (type_and_name, value) = rest.split_once('=')
+            .ok_or_else(|| HcError::of("E0003", &[&cmd]))?;
+        let (typ, name) = split_decl(type_and_name);
+        let vtype = parse_type(typ);
+        if vtype == VarType::Unknown || !type_defined(&vtype, prog) || name.is_empty() {
+            return Err(HcError::of("E0003", &[&cmd]));
+        }
+        declare(&mut scope.decls, name, line.number)?;
+        scope.types.insert(name.to_string(), vtype.clone());
+        let v = eval_const(name, value, prog, &scope.consts)?;
+        if !check_type(&v, &vtype) {
+            return Err(HcError::of("E0101", &[&tr("what.const", &[&name]), &v]).focus(value.trim()));
+        }
+        scope.consts.insert(name.to_string(), v);
+        return Ok(());
+    }
+    if let Some((kind, label, empty, rest)) = COLLECTION_KINDS.iter()
+        .find_map(|&(kind, label, empty)| cmd.strip_prefix(kind).and_then(|r| r.strip_prefix(' ')).map(|r| (kind, label, empty, r)))
+    {
+        let label = tr(label, &[]);
+        let (type_and_name, value) = rest.split_once('=').unwrap_or((rest, ""));
+        let (typ, name) = split_decl(type_and_name);
+        let vtype = parse_type(&format!("{} {}", kind, typ));
+        if !type_defined(&vtype, prog) {
+            return Err(HcError::of("E0004", &[&label, &cmd]));
+        }
+        declare(&mut scope.decls, name, line.number)?;
+        let value = if value.trim().is_empty() { empty } else { value.trim() };
+        let result = expect_type(value, &vtype, prog, scope, &format!("{} {}", label, name));
+        let vtype = refine_type(vtype, infer_type(value, prog, scope).unwrap_or(VarType::Unknown));
+        scope.types.insert(name.to_string(), vtype);
+        return result;
+    }
+    if let Some(expr) = cmd.strip_prefix("say ") {
+        return infer_type(expr, prog, scope).map(|_| ());
+    }
+    if let Some(expr) = cmd.strip_prefix("throw ") {
+        let vtype = infer_type(expr, prog, scope)?;
+        if !assignable(&vtype, &VarType::Str) && !assignable(&vtype, &VarType::Named("Error".to_string())) {
+            return Err(HcError::of("E0134", &[&expr.trim()]).focus(expr.trim()));
+        }
+        return Ok(());
+    }
+    if cmd.starts_with("exit(") && cmd.ends_with(')') {
+        let (_, args) = split_call(cmd);
+        if !args.trim().is_empty() && !assignable(&infer_type(args, prog, scope)?, &VarType::Int) {
+            return Err(HcError::of("E0135", &[&args.trim()]).focus(args.trim()));
+        }
+        return Ok(());
+    }
+    if split_method_call(cmd).is_some_and(|(base, _, _)| is_identifier(base)) {
+        return infer_type(cmd, prog, scope).map(|_| ());
+    }
+    if call_name(cmd).is_some() {
+        return infer_type(cmd, prog, scope).map(|_| ());
+    }
+    if let Some(op) = ["+=", "-=", "*=", "/=", "%="].into_iter().find(|op| cmd.contains(op)) && !cmd.starts_with("call ") {
+        let (name, value) = cmd.split_once(op).unwrap_or((cmd, ""));
+        let name = name.trim();
+        check_not_const(name, |n| scope.consts.contains_key(n))?;
+        if is_identifier(name) && !scope.types.contains_key(name) {
+            return Err(HcError::of("E0202", &[&name]).focus(name));
+        }
+        expect_number(name, infer_type(name, prog, scope)?)?;
+        return expect_number(value.trim(), infer_type(value, prog, scope)?);
+    }
+    if cmd.contains('=') && !["call ", "if ", "while ", "for ", "do ", "switch "].iter().any(|k| cmd.starts_with(k)) {
+        let (target, value) = cmd.split_once('=').unwrap_or((cmd, ""));
+        let target = target.trim();
+        check_not_const(target, |n| scope.consts.contains_key(n))?;
+        if is_identifier(target) && !scope.types.contains_key(target) {
+            return Err(HcError::of("E0202", &[&target]).focus(target));
+        }
+        let target_type = infer_type(target, prog, scope)?;
+        return expect_type(value, &target_type, prog, scope, &tr("what.var", &[&target]));
+    }
+    if cmd.starts_with("call ") {
+        return infer_type(cmd, prog, scope).map(|_| ());
+    }
+    if let Some(cond) = cmd.strip_prefix("if ").and_then(|c| c.strip_suffix(':')) {
+        check_condition(cond, prog, scope)?;
+        // if x is not none: 块内 x 按非可选类型检查
+        let narrowed = cond.trim().strip_suffix(" is not none").map(|n| n.trim())
+            .and_then(|n| match scope.types.get(n) { Some(VarType::Opt(inner)) => Some((n.to_string(), (**inner).clone())), _ => None });
+        if let Some((name, inner)) = narrowed {
+            let original = scope.types.insert(name.clone(), inner);
+            check_block(&body, prog, scope, diags);
+            if let Some(original) = original { scope.types.insert(name, original); }
+        } else {
+            check_block(&body, prog, scope, diags);
+        }
+        return Ok(());
+    }
+    if let Some(cond) = ["while ", "do "].iter().find_map(|k| cmd.strip_prefix(k)).and_then(|c| c.strip_suffix(':')) {
+        check_block(&body, prog, scope, diags);
+        return check_condition(cond, prog, scope);
+    }
+    if let Some(cond) = cmd.strip_prefix("for ").and_then(|c| c.strip_suffix(':')) {
+        let (vars, rest) = cond.split_once(" in ")
+            .ok_or_else(|| HcError::of("E0006", &[&cmd]))?;
+        let vars: Vec<&str> = vars.split(',').map(|v| v.trim()).collect();
+        let item_types = match (infer_type(rest, prog, scope)?, vars.len()) {
+            (VarType::List(t) | VarType::Set(t) | VarType::Deque(t), 1) => vec![*t],
+            (VarType::Map(k, _), 1) => vec![*k],
+            (VarType::Map(k, v), 2) => vec![*k, *v],
+            (VarType::Unknown, n) => vec![VarType::Unknown; n],
+            (VarType::List(_) | VarType::Set(_) | VarType::Deque(_), _) => {
+                return Err(HcError::of("E0006", &[&cmd]));
+            }
+            _ => return Err(HcError::of("E0136", &[&rest.trim()]).focus(rest.trim())),
+        };
+        for (var, vtype) in vars.iter().zip(item_types) {
+            check_not_const(var, |n| scope.consts.contains_key(n))?;
+            scope.types.insert(var.to_string(), vtype);
+        }
+        check_block(&body, prog, scope, diags);
+        return Ok(());
+    }
+    if let Some(subject) = cmd.strip_prefix("switch ").and_then(|c| c.strip_suffix(':')) {
+        let (cases, default_block, j) = collect_cases(block, i, indent);
+        *next = j;
+        let subject_type = infer_type(subject, prog, scope)?;
+        for (label, case_line, body) in &cases {
+            let (pattern, guard) = split_guard(label);
+            // 枚举载荷绑定的名字类型未知；守卫中绑定的名字是被匹配的值
+            for alt in split_top_level(pattern, ',') {
+                let (_, binds) = split_call(&alt);
+                for bind in binds.split(',').map(|b| b.trim()).filter(|b| is_identifier(b) && *b != "_") {
+                    if let Err(e) = check_not_const(bind, |n| scope.consts.contains_key(n)) {
+                        diags.push(check_diagnostic(prog, case_line, e));
+                        continue;
+                    }
+                    scope.types.insert(bind.to_string(), VarType::Unknown);
+                }
+                if guard.is_some() && is_identifier(&alt) && !scope.types.contains_key(&alt) && alt != "_" {
+                    scope.types.insert(alt.clone(), subject_type.clone());
+                }
+            }
+            if let Some(guard) = guard && let Err(e) = check_condition(guard, prog, scope) {
+                diags.push(check_diagnostic(prog, case_line, e));
+            }
+            check_block(body, prog, scope, diags);
+        }
+        if let Some(default_block) = default_block {
+            check_block(&default_block, prog, scope, diags);
+        }
+        return Ok(());
+    }
+    if cmd == "try:" {
+        let (try_block, mut j) = collect_block(block, i, indent);
+        check_block(&try_block, prog, scope, diags);
+        let clause = |j: usize| block.get(j).filter(|l| indent_of(l) == indent).map(|l| l.trim());
+        if let Some(name) = clause(j).and_then(catch_name) {
+            let (catch_block, after) = collect_block(block, j, indent);
+            if let Err(e) = check_not_const(name, |n| scope.consts.contains_key(n)) {
+                diags.push(check_diagnostic(prog, &block[j], e));
+            } else if !name.is_empty() {
+                scope.types.insert(name.to_string(), VarType::Named("Error".to_string()));
+            }
+            check_block(&catch_block, prog, scope, diags);
+            j = after;
+        }
+        if clause(j) == Some("finally:") {
+            let (finally_block, after) = collect_block(block, j, indent);
+            check_block(&finally_block, prog, scope, diags);
+            j = after;
+        }
+        *next = j;
+        return Ok(());
+    }
+    if catch_name(cmd).is_some() || cmd == "finally:" {
+        return Err(HcError::of("E0008", &[&cmd]));
+    }
+    if prog.functions.contains_key(cmd) {
+        return Err(HcError::of("E0009", &[&cmd]));
+    }
+    Err(HcError::of("E0029", &[&cmd]))
+}
+
+// 表达式的类型应当可以赋给 expected
+fn expect_type(expr: &str, expected: &VarType, prog: &Program, scope: &Scope, what: &str) -> Result<(), HcError> {
+    let actual = infer_type(expr, prog, scope)?;
+    if assignable(&actual, expected) {
+        return Ok(());
+    }
+    let err = if actual == none_type() {
+        HcError::of("E0102", &[&what])
+    } else {
+        HcError::of("E0101", &[&what, &tr("what.typed", &[&expr.trim(), &type_name(&actual)])])
+    };
+    Err(err.focus(expr.trim()))
+}
+
+fn expect_number(expr: &str, vtype: VarType) -> Result<(), HcError> {
+    if matches!(vtype, VarType::Int | VarType::Double | VarType::Unknown) {
+        return Ok(());
+    }
+    Err(HcError::of("E0121", &[&expr, &type_name(&vtype)]).focus(expr))
+}
+
+// 类型的书写形式，用于错误信息
+fn type_name(vtype: &VarType) -> String {
+    match vtype {
+        VarType::Int => "int".to_string(),
+        VarType::Double => "double".to_string(),
+        VarType::Str => "str".to_string(),
+        VarType::Bool => "bool".to_string(),
+        VarType::List(inner) => format!("list {}", type_name(inner)),
+        VarType::Map(key, value) => format!("map {} {}", type_name(key), type_name(value)),
+        VarType::Set(inner) => format!("set {}", type_name(inner)),
+        VarType::Deque(inner) => format!("deque {}", type_name(inner)),
+        VarType::Tuple(types) => format!("({})", types.iter().map(type_name).collect::<Vec<_>>().join(", ")),
+        VarType::Opt(inner) if **inner == VarType::Unknown => "none".to_string(),
+        VarType::Opt(inner) => format!("opt {}", type_name(inner)),
+        VarType::Named(name) => name.clone(),
+        VarType::Unknown => "?".to_string(),
+    }
+}
+
+// actual 类型的值能否赋给声明为 declared 的变量；未知类型总是可以
+fn assignable(actual: &VarType, declared: &VarType) -> bool {
+    use VarType::*;
+    match (actual, declared) {
+        (Unknown, _) | (_, Unknown) => true,
+        (Opt(a), Opt(d)) => assignable(a, d),
+        (_, Opt(d)) => assignable(actual, d),
+        (Opt(_), _) => false,
+        (Int, Double) => true,
+        // 列表字面量可以初始化双端队列和集合
+        (List(a), List(d) | Deque(d) | Set(d)) | (Deque(a), Deque(d) | List(d)) | (Set(a), Set(d)) => assignable(a, d),
+        (Map(ak, av), Map(dk, dv)) => assignable(ak, dk) && assignable(av, dv),
+        (Tuple(a), Tuple(d)) => a.len() == d.len() && a.iter().zip(d).all(|(a, d)| assignable(a, d)),
+        _ => actual == declared,
+    }
+}
+
+// 多个值的共同类型（列表元素等）；不一致时为未知
+fn common_type(types: Vec<VarType>) -> VarType {
+    let mut types = types.into_iter();
+    let Some(first) = types.next() else { return VarType::Unknown };
+    types.fold(first, |acc, t| if acc == t { acc } else if assignable(&acc, &t) && assignable(&t, &acc) { VarType::Unknown } else {
+        match (&acc, &t) {
+            (VarType::Int, VarType::Double) | (VarType::Double, VarType::Int) => VarType::Double,
+            _ => VarType::Unknown,
+        }
+    })
+}
+
+// 检查条件表达式，对应 eval_condition
+fn check_condition(cond: &str, prog: &Program, scope: &Scope) -> Result<(), HcError> {
+    for suffix in [" is not none", " is none"] {
+        if let Some(subject) = cond.strip_suffix(suffix) {
+            return infer_type(subject, prog, scope).map(|_| ());
+        }
+    }
+    let ops = [">=", "<=", "==", "!=", ">", "<"];
+    for idx in top_level_indices(cond) {
+        let Some(op) = ops.iter().find(|op| cond[idx..].starts_with(**op)) else { continue };
+        infer_type(&cond[..idx], prog, scope)?;
+        infer_type(&cond[idx+op.len()..], prog, scope)?;
+        return Ok(());
+    }
+    infer_type(cond, prog, scope).map(|_| ())
+}
+
+// 检查函数调用的参数个数和类型，返回 ->return:: 声明的类型
+fn check_call(call_expr: &str, prog: &Program, scope: &Scope) -> Result<VarType, HcError> {
+    let (fname, args) = split_call(call_expr);
+    let Some((params, ret_type)) = prog.function_sigs.get(fname) else {
+        return Err(HcError::of("E0203", &[&fname]).focus(fname));
+    };
+    let args: Vec<String> = split_top_level(args, ',').into_iter().filter(|s| !s.is_empty()).collect();
+    if args.len() != params.len() {
+        return Err(HcError::of("E0107", &[&fname, &params.len(), &args.len()]).focus(call_expr));
+    }
+    for ((ptype, pname), arg) in params.iter().zip(&args) {
+        expect_type(arg, &parse_type(ptype), prog, scope, &tr("what.param", &[&fname, &pname]))?;
+    }
+    Ok(match ret_type {
+        Some(ret_type) => parse_type(ret_type),
+        None if prog.function_returns.get(fname).is_none_or(|r| r.is_empty()) => none_type(),
+        None => VarType::Unknown,
+    })
+}
+
+// 推断表达式的类型，对应 eval_value 的各个分支；无法确定时返回 Unknown
+fn infer_type(expr: &str, prog: &Program, scope: &Scope) -> Result<VarType, HcError> {
+    use VarType::*;
+    let expr = expr.trim();
+    if expr.is_empty() { return Err(HcError::of("E0010", &[])); }
+    if is_string_literal(expr) || is_input_call(expr) { return Ok(Str); }
+    if let Some((pos, op)) = find_arith_op(expr) {
+        let (l, r) = (expr[..pos].trim(), expr[pos+1..].trim());
+        let (lt, rt) = (infer_type(l, prog, scope)?, infer_type(r, prog, scope)?);
+        expect_number(l, lt.clone())?;
+        expect_number(r, rt.clone())?;
+        return Ok(match (lt, rt) {
+            // 整数相除的结果可能是小数
+            (Int, Int) if op != '/' => Int,
+            (Unknown, _) | (_, Unknown) | (Int, Int) => Unknown,
+            _ => Double,
+        });
+    }
+    if let Some(rest) = expr.strip_prefix("call ") {
+        let rest = rest.trim();
+        return match call_name(rest) {
+            Some(_) => check_call(rest, prog, scope),
+            None => infer_type(rest, prog, scope),
+        };
+    }
+    if is_function_call(expr, prog) {
+        return check_call(expr, prog, scope);
+    }
+    if let Some(operand) = expr.strip_prefix('-') {
+        let vtype = infer_type(operand, prog, scope)?;
+        expect_number(operand.trim(), vtype.clone())?;
+        return Ok(vtype);
+    }
+    if let Some((enum_name, rest)) = expr.split_once('.') && let Some(variants) = prog.enums.get(enum_name.trim()) {
+        let (variant, args) = split_call(rest.trim());
+        let Some((_, fields)) = variants.iter().find(|(v, _)| v == variant) else {
+            return Err(HcError::of("E0208", &[&enum_name.trim(), &variant]).focus(expr));
+        };
+        check_fields(&format!("{}.{}", enum_name.trim(), variant), fields, args, prog, scope)?;
+        return Ok(Named(enum_name.trim().to_string()));
+    }
+    if let Some((base, method, args)) = split_method_call(expr) {
+        if MUTATING_METHODS.contains(&method) {
+            check_not_const(base, |n| scope.consts.contains_key(n))?;
+        }
+        let base_type = infer_type(base, prog, scope)?;
+        let arg_types = split_top_level(args, ',').into_iter().filter(|s| !s.is_empty())
+            .map(|arg| infer_type(&arg, prog, scope))
+            .collect::<Result<Vec<_>, _>>()?;
+        return infer_method(&base_type, base, method, &arg_types);
+    }
+    if let Some((base, field)) = split_field_access(expr) {
+        return match infer_type(base, prog, scope)? {
+            Named(name) if prog.structs.contains_key(&name) => prog.structs[&name].iter().find(|(_, f)| f == field)
+                .map(|(ftype, _)| parse_type(ftype))
+                .ok_or_else(|| HcError::of("E0206", &[&name, &field]).focus(field)),
+            Unknown => Ok(Unknown),
+            Opt(_) => Err(HcError::of("E0125", &[&base]).focus(base)),
+            _ => Err(HcError::of("E0129", &[&base, &field]).focus(expr)),
+        };
+    }
+    if let Some((base, idx)) = split_index_suffix(expr) {
+        let idx_type = infer_type(idx, prog, scope)?;
+        return match infer_type(base, prog, scope)? {
+            List(inner) | Deque(inner) => {
+                expect_type(idx, &Int, prog, scope, &tr("what.index", &[]))?;
+                Ok(*inner)
+            }
+            Map(key, value) => {
+                if !assignable(&idx_type, &key) {
+                    return Err(HcError::of("E0113", &[&idx.trim()]).focus(idx.trim()));
+                }
+                Ok(*value)
+            }
+            Tuple(types) => match idx.trim().parse::<usize>() {
+                Ok(n) if n < types.len() => Ok(types[n].clone()),
+                Ok(n) => Err(HcError::of("E0402", &[&n, &types.len()]).focus(idx.trim())),
+                Err(_) => Ok(Unknown),
+            },
+            Unknown => Ok(Unknown),
+            Opt(_) => Err(HcError::of("E0126", &[&base]).focus(base)),
+            other => Err(HcError::of("E0112", &[&base, &type_name(&other)]).focus(base)),
+        };
+    }
+    if expr.ends_with(')') && let (name, args) = split_call(expr) && let Some(fields) = prog.structs.get(name) {
+        check_fields(name, fields, args, prog, scope)?;
+        return Ok(Named(name.to_string()));
+    }
+    if let Some(items) = parse_list_value(expr) {
+        let types = items.iter().map(|item| infer_type(item, prog, scope)).collect::<Result<Vec<_>, _>>()?;
+        return Ok(List(Box::new(common_type(types))));
+    }
+    if let Some(entries) = parse_map_value(expr) {
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        for (k, v) in &entries {
+            keys.push(infer_type(k, prog, scope)?);
+            values.push(infer_type(v, prog, scope)?);
+        }
+        return Ok(Map(Box::new(common_type(keys)), Box::new(common_type(values))));
+    }
+    if let Some(items) = parse_set_value(expr) {
+        let types = items.iter().map(|item| infer_type(item, prog, scope)).collect::<Result<Vec<_>, _>>()?;
+        return Ok(Set(Box::new(common_type(types))));
+    }
+    if let Some(items) = parse_tuple_value(expr) {
+        return Ok(Tuple(items.iter().map(|item| infer_type(item, prog, scope)).collect::<Result<Vec<_>, _>>()?));
+    }
+    if let Some(inner) = expr.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        return infer_type(inner, prog, scope);
+    }
+    if expr == "trace()" { return Ok(List(Box::new(Str))); }
+    if expr.parse::<i64>().is_ok() { return Ok(Int); }
+    if expr.parse::<f64>().is_ok() { return Ok(Double); }
+    if expr == "true" || expr == "false" { return Ok(Bool); }
+    if expr == "none" { return Ok(none_type()); }
+    if let Some(vtype) = scope.types.get(expr) {
+        return Ok(vtype.clone());
+    }
+    if is_identifier(expr) {
+        return Err(HcError::of("E0201", &[&expr]).focus(expr));
+    }
+    if let Some(fname) = call_name(expr) {
+        return Err(HcError::of("E0203", &[&fname]).focus(fname));
+    }
+    Ok(Unknown)
+}
+
+// 检查结构体/枚举载荷的构造参数，规则同 construct_struct
+fn check_fields(name: &str, fields: &[(String, String)], args: &str, prog: &Program, scope: &Scope) -> Result<(), HcError> {
+    let args: Vec<String> = split_top_level(args, ',').into_iter().filter(|s| !s.is_empty()).collect();
+    let named: Vec<(&str, &str)> = args.iter()
+        .filter_map(|arg| arg.split_once(':').map(|(k, v)| (k.trim(), v.trim())).filter(|(k, _)| is_identifier(k)))
+        .collect();
+    let by_name = !args.is_empty() && named.len() == args.len();
+    if args.len() > fields.len() {
+        return Err(HcError::of("E0108", &[&name, &fields.len(), &args.len()]));
+    }
+    for (idx, (ftype, fname)) in fields.iter().enumerate() {
+        let expr = if by_name {
+            named.iter().find(|(k, _)| k == fname).map(|(_, v)| *v)
+        } else {
+            args.get(idx).map(|s| s.as_str())
+        };
+        let expr = expr.ok_or_else(|| HcError::of("E0207", &[&name, &fname]))?;
+        expect_type(expr, &parse_type(ftype), prog, scope, &tr("what.field", &[&name, &fname]))?;
+    }
+    Ok(())
+}
+
+// 方法调用的结果类型，规则同内置方法模块；加入集合/双端队列的元素类型在这里检查
+fn infer_method(base_type: &VarType, base: &str, method: &str, args: &[VarType]) -> Result<VarType, HcError> {
+    use VarType::*;
+    let unsupported = || HcError::of("E0119", &[&base, &type_name(base_type), &method]);
+    let check_item = |item: &VarType, inner: &VarType| if assignable(item, inner) {
+        Ok(())
+    } else {
+        Err(HcError::of("E0103", &[&base, &type_name(item)]))
+    };
+    match (base_type, method, args) {
+        (Unknown, _, _) => Ok(Unknown),
+        (Opt(_), _, _) => Err(HcError::of("E0127", &[&base]).focus(base)),
+        (Set(inner), "add", [item]) | (Deque(inner), "push_back" | "push_front", [item]) => check_item(item, inner).map(|_| Unknown),
+        _ if let Some(vtype) = METHOD_MODULES.iter().find_map(|module| (module.infer)(base_type, method, args)) => Ok(vtype),
+        _ => Err(unsupported().focus(method)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // 依次检查每一行的缩进，返回每一行的结果
+    fn indent_codes(lines: &[&str]) -> Vec<Option<&'static str>> {
+        let mut stack = Vec::new();
+        let mut opens_block = false;
+        lines.iter().map(|line| check_indent(&mut stack, &mut opens_block, line)).collect()
+    }
+
+    // 测试用的定义：结构体、枚举和几个函数
+    const DEFINITIONS: &str = r#"struct Point: int x, int y
+struct Line: Point a, Point b
+enum Color: Red, Green, Blue
+enum Shape: Circle(int r), Square(int side)
+
+function divmod(int a, int b)->return::(int, int):
+    var int r = a % b
+    var int q = (a - r) / b
+    end(q, r)
+
+function where(int n)->return::list str:
+    var int m = n
+    end(trace())
+
+function broken(int n):
+    list str m = call where(n)
+    var int z = n / 0
+    end()
+
+start:
+    say 1
+end
+"#;
+
+    // 把源码写到临时文件中再加载，返回程序、start 块和加载时发现的错误
+    fn load(src: &str) -> (Program, Vec<SourceLine>, Vec<Diagnostic>) {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("hitcode-test-{}-{}.hc", process::id(), n));
+        std::fs::write(&path, src).unwrap();
+        let loaded = load_program(path.to_str().unwrap(), None);
+        std::fs::remove_file(&path).ok();
+        loaded
+    }
+
+    // 加载测试用的定义
+    fn program() -> Program {
+        let (prog, _, errors) = load(DEFINITIONS);
+        assert!(errors.is_empty());
+        Program { file: "test.hc".to_string(), ..prog }
+    }
+
+    // 加载并静态检查，返回所有错误码
+    fn check_codes(src: &str) -> Vec<&'static str> {
+        let (prog, start, load_errors) = load(src);
+        load_errors.iter().chain(&check_program(&prog, &start)).map(|d| d.code).collect()
+    }
+
+    // 把源码拆成带行号的源码行
+    fn lines(src: &str) -> Vec<SourceLine> {
+        src.lines().enumerate().map(|(i, text)| SourceLine { text: text.to_string(), number: i + 1 }).collect()
+    }
+
+    // 在给定的运行时状态下依次执行若干行语句，出错时返回错误
+    fn exec(src: &str, rt: &mut Runtime) -> Result<(), HcError> {
+        eval_block(&lines(src), &program(), rt, 0)
+    }
+
+    // 依次执行若干行语句，返回执行后的运行时状态
+    fn run(src: &str) -> Runtime {
+        let mut rt = Runtime::default();
+        exec(src, &mut rt).unwrap();
+        rt
+    }
+
+    // 在给定的运行时状态下求值表达式，出错时返回错误信息
+    fn eval(expr: &str, rt: &mut Runtime) -> Result<String, String> {
+        eval_value(expr, &program(), rt).map_err(|e| e.message)
+    }
+
+    #[test]
+    fn nested_list_types() {
+        let matrix = parse_type("list list int");
+        assert_eq!(matrix, VarType::List(Box::new(VarType::List(Box::new(VarType::Int)))));
+        assert!(check_type("[[1, 2], [3]]", &matrix));
+        assert!(!check_type(r#"[[1, "a"]]"#, &matrix));
+        assert!(!check_type("[1, 2]", &matrix));
+    }
+
+    #[test]
+    fn list_literals_are_expressions() {
+        let mut rt = run("var int n = 2\n");
+        assert_eq!(eval("[n, [n + 1, 4]]", &mut rt), Ok("[2, [3, 4]]".to_string()));
+        assert_eq!(eval("[[1, 2], [3, 4]][1][0]", &mut rt), Ok("3".to_string()));
+        assert_eq!(eval(r#"["a, b", "c"][0]"#, &mut rt), Ok(r#""a, b""#.to_string()));
+        assert!(eval("[1, 2][5]", &mut rt).is_err());
+    }
+
+    #[test]
+    fn nested_index_assignment() {
+        let rt = run("list list int m = [[1, 2], [3, 4]]\nm[0][1] = 9\nm[1] = [5]\n");
+        assert_eq!(rt.lists["m"], vec!["[1, 9]", "[5]"]);
+    }
+
+    #[test]
+    fn map_keeps_insertion_order() {
+        let src = "map str int scores = {\"b\": 2, \"a\": 1}\nscores[\"c\"] = 3\nscores[\"b\"] = 5\nscores.remove(\"a\")\n\
+                   var int total = 0\nfor k, v in scores:\n    total += v\n";
+        let mut rt = run(src);
+        assert!(exec(r#"scores["d"] = "x""#, &mut rt).is_err());
+        assert_eq!(eval("scores", &mut rt), Ok(r#"{"b": 5, "c": 3}"#.to_string()));
+        assert_eq!(eval("total", &mut rt), Ok("8".to_string()));
+        assert_eq!(eval(r#"scores["c"]"#, &mut rt), Ok("3".to_string()));
+        assert!(eval(r#"scores["a"]"#, &mut rt).is_err());
+        assert_eq!(eval(r#"scores.has("b")"#, &mut rt), Ok("true".to_string()));
+        assert_eq!(eval("scores.keys()", &mut rt), Ok(r#"["b", "c"]"#.to_string()));
+        assert_eq!(eval("scores.values()", &mut rt), Ok("[5, 3]".to_string()));
+    }
+
+    #[test]
+    fn map_types_are_checked() {
+        let map_type = parse_type("map str list int");
+        assert!(check_type(r#"{"a": [1, 2], "b": []}"#, &map_type));
+        assert!(!check_type(r#"{"a": 1}"#, &map_type));
+        assert!(!check_type("{1: [1]}", &map_type));
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut rt = run("set int a = {1, 2, 3, 2}\nset int b = [3, 4]\na.add(5)\na.remove(1)\n");
+        assert_eq!(eval("a", &mut rt), Ok("{2, 3, 5}".to_string()));
+        assert_eq!(eval("a.len()", &mut rt), Ok("3".to_string()));
+        assert_eq!(eval("a.has(3)", &mut rt), Ok("true".to_string()));
+        assert_eq!(eval("a.union(b)", &mut rt), Ok("{2, 3, 5, 4}".to_string()));
+        assert_eq!(eval("a.intersection(b)", &mut rt), Ok("{3}".to_string()));
+        assert_eq!(eval("a.difference(b)", &mut rt), Ok("{2, 5}".to_string()));
+    }
+
+    #[test]
+    fn deque_pushes_and_pops_at_both_ends() {
+        let mut rt = run("deque int q = [2]\nq.push_back(3)\nq.push_front(1)\nvar int sum = 0\nfor x in q:\n    sum += x\n");
+        assert_eq!(eval("q", &mut rt), Ok("[1, 2, 3]".to_string()));
+        assert_eq!(eval("sum", &mut rt), Ok("6".to_string()));
+        assert_eq!(eval("q.pop_front()", &mut rt), Ok("1".to_string()));
+        assert_eq!(eval("q.pop_back()", &mut rt), Ok("3".to_string()));
+        assert_eq!(eval("q.len()", &mut rt), Ok("1".to_string()));
+        assert!(eval(r#"q.push_back("x")"#, &mut rt).is_err());
+    }
+
+    #[test]
+    fn collection_methods_are_method_modules() {
+        let call = |val: &str, method: &str, args: &[&str]| {
+            call_value_method(val, method, &args.iter().map(|a| a.to_string()).collect::<Vec<_>>()).map_err(|e| e.code)
+        };
+        assert_eq!(call(r#"{"a": 1, "b": 2}"#, "values", &[]), Ok("[1, 2]".to_string()));
+        assert_eq!(call("{}", "get", &["1"]), Ok("none".to_string()));
+        assert_eq!(call("{1, 2}", "difference", &["[2]"]), Ok("{1}".to_string()));
+        assert_eq!(call("[1, 2]", "len", &[]), Ok("2".to_string()));
+        assert_eq!(call("5", "len", &[]), Err("E0114"));
+        assert_eq!(call("5", "has", &["1"]), Err("E0115"));
+        assert_eq!(call("{1, 2}", "add", &["3"]), Err("E0117"));
+        assert_eq!(call("[1, 2]", "has", &["1"]), Err("E0118"));
+        for (typ, method, args) in [("map str int", "keys", vec![]), ("set int", "remove", vec![VarType::Int]), ("deque int", "pop_front", vec![])] {
+            assert!(METHOD_MODULES.iter().any(|module| (module.infer)(&parse_type(typ), method, &args).is_some()), "{}.{}", typ, method);
+        }
+        assert_eq!(check_codes("start:\n    set int s = {1}\n    s.add(\"x\")\n    deque str q = []\n    q.push_front(2)\nend\n"), vec!["E0103", "E0103"]);
+    }
+
+    #[test]
+    fn struct_construction_and_fields() {
+        let mut rt = run("var Point p = Point(1, 2)\nvar Point q = Point(y: 5, x: 4)\np.x = 3\nq.y += 1\n\
+                          var Line l = Line(p, q)\nl.b.x = 7\n");
+        assert_eq!(eval("p", &mut rt), Ok("Point(x: 3, y: 2)".to_string()));
+        assert_eq!(eval("q.y", &mut rt), Ok("6".to_string()));
+        assert_eq!(eval("l.b.x + l.a.x", &mut rt), Ok("10".to_string()));
+        assert!(eval("p.z", &mut rt).is_err());
+        assert!(eval(r#"Point(1, "a")"#, &mut rt).is_err());
+        assert!(eval("Point(1)", &mut rt).is_err());
+        assert!(check_type("Point(x: 1, y: 2)", &parse_type("Point")));
+        assert!(!check_type("Point(x: 1, y: 2)", &parse_type("Line")));
+    }
+
+    #[test]
+    fn enum_variants_and_payloads() {
+        let mut rt = Runtime::default();
+        assert_eq!(eval("Color.Green", &mut rt), Ok("Color.Green".to_string()));
+        assert_eq!(eval("Shape.Circle(3)", &mut rt), Ok("Shape.Circle(r: 3)".to_string()));
+        assert!(eval("Color.Purple", &mut rt).is_err());
+        assert!(eval(r#"Shape.Circle("a")"#, &mut rt).is_err());
+        assert!(check_type("Color.Red", &parse_type("Color")));
+        assert!(!check_type("Color.Red", &parse_type("Shape")));
+    }
+
+    #[test]
+    fn switch_binds_enum_payloads() {
+        let mut rt = run("var Shape s = Shape.Square(4)\nvar int area = 0\nswitch s:\n    case Circle(r):\n        area = r * r * 3\n    case Shape.Square(side):\n        area = side * side\n");
+        assert_eq!(eval("area", &mut rt), Ok("16".to_string()));
+    }
+
+    #[test]
+    fn match_case_values_ranges_and_guards() {
+        let prog = program();
+        let mut rt = Runtime::default();
+        let mut case = |label: &str, val: &str| match_case(label, val, &prog, &mut rt).map_err(|_| ());
+        assert_eq!(case("1, 2, 3", "2"), Ok(Some(vec![])));
+        assert_eq!(case("1, 2, 3", "4"), Ok(None));
+        assert_eq!(case(r#""a", "b""#, r#""b""#), Ok(Some(vec![])));
+        assert_eq!(case("1..5", "4"), Ok(Some(vec![])));
+        assert_eq!(case("1..5", "5"), Ok(None));
+        assert_eq!(case("1..=5", "5"), Ok(Some(vec![])));
+        assert_eq!(case(r#""a".."z""#, "1"), Err(()));
+        assert_eq!(case("n if n > 10", "12"), Ok(Some(vec![("n".to_string(), "12".to_string())])));
+        assert_eq!(case("n if n > 10", "3"), Ok(None));
+        assert_eq!(case("Color.Red", "Color.Red"), Ok(Some(vec![])));
+        assert_eq!(case("Green", "Color.Red"), Ok(None));
+        let circle = "Shape.Circle(r: 3)";
+        assert_eq!(case("Shape.Circle(r)", circle), Ok(Some(vec![("r".to_string(), "3".to_string())])));
+        assert_eq!(case("Circle(_)", circle), Ok(Some(vec![])));
+        assert_eq!(case("Circle(r) if r > 5", circle), Ok(None));
+        assert_eq!(case("Square(s)", circle), Ok(None));
+    }
+
+    #[test]
+    fn tuples_and_destructuring() {
+        let mut rt = run("var (int q, int r) = call divmod(7, 2)\nvar (int, str) t = (1, \"a\")\n");
+        assert!(exec("var (int x, int y) = (1, 2, 3)", &mut rt).is_err());
+        assert_eq!(eval("q", &mut rt), Ok("3".to_string()));
+        assert_eq!(eval("r", &mut rt), Ok("1".to_string()));
+        assert_eq!(eval("t", &mut rt), Ok(r#"(1, "a")"#.to_string()));
+        assert_eq!(eval("t[1]", &mut rt), Ok(r#""a""#.to_string()));
+        assert!(eval("t[2]", &mut rt).is_err());
+        assert!(eval("x", &mut rt).is_err());
+        assert_eq!(eval("call divmod(9, 4)", &mut rt), Ok("(2, 1)".to_string()));
+        assert!(check_type("(1, 2)", &parse_type("(int, int)")));
+        assert!(!check_type("(1, 2)", &parse_type("(int, str)")));
+        assert!(!check_type("(1, 2, 3)", &parse_type("(int, int)")));
+    }
+
+    #[test]
+    fn opt_values_and_none() {
+        let mut rt = run("var opt int idx = none\nvar int hits = 0\nif idx is none:\n    hits += 1\nidx = 4\nif idx is not none:\n    var int next = idx + 1\n");
+        assert_eq!(eval("hits", &mut rt), Ok("1".to_string()));
+        assert_eq!(eval("idx", &mut rt), Ok("4".to_string()));
+        assert_eq!(eval("next", &mut rt), Ok("5".to_string()));
+        assert!(check_type("none", &parse_type("opt int")));
+        assert!(check_type("3", &parse_type("opt int")));
+        assert!(!check_type("none", &parse_type("int")));
+        let mut rt = run("var opt int missing = none\nmap str int m = {\"a\": 1}\n");
+        assert!(eval("missing + 1", &mut rt).is_err());
+        assert!(exec("var int bad = missing", &mut rt).is_err());
+        assert_eq!(eval(r#"m.get("b")"#, &mut rt), Ok("none".to_string()));
+    }
+
+    #[test]
+    fn try_catch_finally() {
+        let src = r#"var str k1 = ""
+var int steps = 0
+try:
+    var int z = 1 / 0
+    steps += 100
+catch err:
+    k1 = err.kind
+finally:
+    steps += 1
+var str k2 = ""
+var str m2 = ""
+try:
+    throw Error(kind: "MyError", message: "boom")
+catch e:
+    k2 = e.kind
+    m2 = e.message
+var str k3 = ""
+var str m3 = ""
+try:
+    try:
+        throw "inner"
+    catch e:
+        throw e
+    finally:
+        steps += 10
+catch outer:
+    k3 = outer.kind
+    m3 = outer.message
+"#;
+        let mut rt = run(src);
+        assert_eq!(eval("k1", &mut rt), Ok(r#""ZeroDivisionError""#.to_string()));
+        assert_eq!(eval("steps", &mut rt), Ok("11".to_string()));
+        assert_eq!(eval("k2", &mut rt), Ok(r#""MyError""#.to_string()));
+        assert_eq!(eval("m2", &mut rt), Ok(r#""boom""#.to_string()));
+        assert_eq!(eval("k3", &mut rt), Ok(r#""Error""#.to_string()));
+        assert_eq!(eval("m3", &mut rt), Ok(r#""inner""#.to_string()));
+        // 消息中的双引号经过转义，catch 得到的消息与抛出时相同
+        let mut rt = run("var str m = \"\"\ntry:\n    throw \"say \\\"hi\\\"\"\ncatch e:\n    m = e.message\n");
+        assert_eq!(eval("m.len()", &mut rt), Ok("8".to_string()));
+        assert_eq!(eval(r#"m.contains("\"")"#, &mut rt), Ok("true".to_string()));
+    }
+
+    #[test]
+    fn diagnostic_marks_the_focus() {
+        let source = SourceLine { text: r#"    var int 数量 = "abc""#.to_string(), number: 3 };
+        let diag = Diagnostic::new(Severity::Error, "E0100", "类型不匹配".to_string(), "demo.hc", &source, Some(r#""abc""#), Keywords::En);
+        assert_eq!((diag.line, diag.column, diag.width), (3, 18, 5));
+        let expected = [
+            "error[E0100]: 类型不匹配",
+            " --> demo.hc:3:18",
+            "  |",
+            r#"3 |     var int 数量 = "abc""#,
+            "  |                    ^^^^^",
+        ];
+        assert_eq!(diag.to_string(), expected.join("\n"));
+        // 找不到片段时标出整条语句
+        let diag = Diagnostic::new(Severity::Warning, "W0001", String::new(), "demo.hc", &source, Some("y"), Keywords::En);
+        assert_eq!((diag.column, diag.width), (5, 18));
+        assert_eq!(find_token("xs = x", "x"), Some(5));
+    }
+
+    #[test]
+    fn runtime_errors_carry_the_source_line() {
+        let err = exec("var int a = 1\nif a > 0:\n    var int b = a / 0\n", &mut Runtime::default()).unwrap_err();
+        assert_eq!(err.kind.code(), "E0600");
+        assert_eq!(err.trace.source.map(|s| s.number), Some(3));
+    }
+
+    #[test]
+    fn unrecognized_statements_are_errors() {
+        let mut rt = run("var int n = 1\n");
+        assert_eq!(exec("sya n", &mut rt).map_err(|e| e.code), Err("E0029"));
+        assert_eq!(exec("n +", &mut rt).map_err(|e| e.code), Err("E0029"));
+        assert_eq!(check_codes("start:\n    var int n = 1\n    sya n\nend\n"), vec!["E0029"]);
+    }
+
+    #[test]
+    fn indented_comments_are_skipped() {
+        let (_, start, errors) = load("start:\n    # 注释\n    ## 也是注释\n    say 1\nend\n");
+        assert!(errors.is_empty());
+        assert_eq!(start.len(), 1);
+        assert!(check_codes(include_str!("hw.hc")).is_empty());
+    }
+
+    #[test]
+    fn argument_count_is_checked_at_runtime() {
+        let mut rt = Runtime::default();
+        assert_eq!(exec("var t = divmod(1)", &mut rt).map_err(|e| e.code), Err("E0107"));
+        assert_eq!(exec("var t = call divmod(1, 2, 3)", &mut rt).map_err(|e| e.code), Err("E0107"));
+        assert!(exec("var t = divmod(1, 2)", &mut rt).is_ok());
+    }
+
+    #[test]
+    fn uncaught_errors_stop_with_their_exit_code() {
+        let mut rt = Runtime::default();
+        let err = exec("var int n = 1\nvar int m = missing\nn = 2\n", &mut rt).unwrap_err();
+        assert_eq!((err.kind.code(), err.kind.exit_code()), ("E0200", 4));
+        assert_eq!(eval("n", &mut rt), Ok("1".to_string()));
+        let err = exec("throw \"boom\"", &mut rt).unwrap_err();
+        assert_eq!((err.kind.name(), err.kind.exit_code()), ("Error", 1));
+        let err = exec("exit(\"a\")", &mut rt).unwrap_err();
+        assert_eq!(err.kind.exit_code(), 3);
+        // 被捕获的错误不会结束程序
+        assert!(exec("try:\n    var int z = 1 / 0\ncatch:\n    n = 3\n", &mut rt).is_ok());
+        assert_eq!(eval("n", &mut rt), Ok("3".to_string()));
+    }
+
+    #[test]
+    fn trace_lists_the_call_stack() {
+        let mut rt = run("var int a = 1\nlist str t = trace()\nlist str u = call where(a + 1)\n");
+        assert_eq!(eval("t", &mut rt), Ok(r#"["start (test.hc:2)"]"#.to_string()));
+        assert_eq!(eval("u.len()", &mut rt), Ok("2".to_string()));
+        assert_eq!(eval("u", &mut rt), Ok(r#"["where(n: 2) (test.hc:13)", "start (test.hc:3)"]"#.to_string()));
+        let err = exec("var int a = 1\n\ncall broken(a)\n", &mut rt).unwrap_err();
+        assert_eq!(err.trace.source.map(|s| s.number), Some(17));
+        assert_eq!(stack_lines(&program(), &err.trace.frames.unwrap(), 17), vec!["broken(n: 1) (test.hc:17)", "start (test.hc:3)"]);
+    }
+
+    #[test]
+    fn errors_in_end_point_at_the_end_line() {
+        let (prog, start, _) = load("function f(int n)->return::int:\n    var int m = n\n    end(m / 0)\n\nfunction g()->return::int:\n    end(\"x\")\n\nstart:\n    call f(1)\n    call g()\nend\n");
+        for (stmt, code, end_line) in [(&start[0], "E0601", 3), (&start[1], "E0106", 6)] {
+            let err = eval_block(std::slice::from_ref(stmt), &prog, &mut Runtime::default(), 0).unwrap_err();
+            assert_eq!((err.code, err.trace.source.map(|s| s.number), err.trace.line), (code, Some(end_line), end_line));
+        }
+    }
+
+    #[test]
+    fn check_program_reports_type_errors() {
+        assert_eq!(check_codes(DEFINITIONS), Vec::<&str>::new());
+        assert_eq!(check_codes("start:\n    var int x = \"a\"\n    var str y = x\nend\n"), vec!["E0101", "E0101"]);
+        assert_eq!(check_codes("start:\n    say missing\n    call nothing()\nend\n"), vec!["E0201", "E0203"]);
+        let src = format!("{}\nfunction use():\n    var (int q, int r) = call divmod(7, \"2\")\n    var Point p = Point(1)\n    end()\n", DEFINITIONS);
+        assert_eq!(check_codes(&src), vec!["E0101", "E0207"]);
+    }
+
+    #[test]
+    fn declarations_infer_their_type() {
+        let mut rt = run("var x = 5\nvar s = \"hi\"\nlist xs = [1, 2]\nvar t = call divmod(7, 2)\nvar p = Point(1, 2)\n");
+        assert_eq!(rt.var_types["x"], VarType::Int);
+        assert_eq!(rt.var_types["s"], VarType::Str);
+        assert_eq!(rt.var_types["xs"], parse_type("list int"));
+        assert_eq!(rt.var_types["t"], parse_type("(int, int)"));
+        assert_eq!(rt.var_types["p"], parse_type("Point"));
+        // 推断出的类型之后同样会检查
+        assert!(exec("x = \"hi\"", &mut rt).is_err());
+        assert!(exec("xs[0] = 1.5", &mut rt).is_err());
+        assert!(exec("var n = none", &mut rt).is_err());
+        assert_eq!(check_codes("start:\n    var x = 5\n    x = \"hi\"\nend\n"), vec!["E0101"]);
+    }
+
+    #[test]
+    fn constants_are_immutable() {
+        let mut rt = run("const int N = 4\nconst int M = N * 2\nconst list int XS = [1, 2]\nconst set int S = {1}\n");
+        assert_eq!(eval("M", &mut rt), Ok("8".to_string()));
+        for stmt in ["N = 5", "N += 1", "XS[0] = 3", "S.add(2)"] {
+            assert!(exec(stmt, &mut rt).is_err(), "{}", stmt);
+        }
+        // for、case 和 catch 绑定的名字也不能是常量
+        let bindings = [
+            "for N in [1, 2]:\n    var int a = N",
+            "switch Shape.Circle(2):\n    case Circle(N):\n        var int a = N",
+            "try:\n    throw \"x\"\ncatch N:\n    var int a = 1",
+        ];
+        for stmt in bindings {
+            assert_eq!(exec(stmt, &mut rt).map_err(|e| e.code), Err("E0130"), "{}", stmt);
+            let src = format!("enum Shape: Circle(int r)\nconst int N = 4\nstart:\n    {}\nend\n", stmt.replace('\n', "\n    "));
+            assert_eq!(check_codes(&src), vec!["E0130"], "{}", stmt);
+        }
+        assert_eq!(eval("N", &mut rt), Ok("4".to_string()));
+        assert_eq!(eval("XS", &mut rt), Ok("[1, 2]".to_string()));
+        assert_eq!(eval("S", &mut rt), Ok("{1}".to_string()));
+        let codes = check_codes("start:\n    const int N = 4\n    N = 5\n    var int x = 1\n    const int K = x + 1\n    var int N = 2\n    const int M = 1\n    const int M = 2\nend\n");
+        assert_eq!(codes, vec!["E0130", "E0011", "E0210", "E0210"]);
+    }
+
+    #[test]
+    fn program_structure_errors() {
+        assert_eq!(check_codes("say 1\nstart:\n    say 2\nend\n"), vec!["E0015"]);
+        assert_eq!(check_codes("function f():\n    say 1\n"), vec!["E0012", "E0016"]);
+        assert_eq!(check_codes("start:\n    say 1\nend\nstart:\n    say 2\nend\n"), vec!["E0014"]);
+        assert_eq!(check_codes("start:\n    say 1\n"), vec!["E0013"]);
+        assert_eq!(check_codes("start:\n    say 1\nfunction f():\n    end()\n"), vec!["E0013"]);
+    }
+
+    #[test]
+    fn module_level_declarations() {
+        let src = "const int LIMIT = 3\nvar str greeting = \"hi\"\n\nfunction show(int n)->return::int:\n    var str g = greeting\n    end(n + LIMIT)\n\n\
+                   start:\n    var int r = call show(1)\nend\n";
+        let (prog, start, errors) = load(src);
+        assert!(errors.is_empty());
+        let mut rt = Runtime::default();
+        eval_block(&prog.globals, &prog, &mut rt, 0).unwrap();
+        eval_block(&start, &prog, &mut rt, 0).unwrap();
+        assert_eq!(rt.variables["r"], "4");
+        assert_eq!(check_codes(src), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn functions_keep_writes_to_module_level_variables() {
+        let src = "var int count = 0\ndeque int seen = []\nvar int other = 5\n\n\
+                   function bump(int n)->return::int:\n    count += n\n    seen.push_back(n)\n    var int other = 1\n    other += 1\n    end(count)\n\n\
+                   start:\n    var int r = bump(2)\n    r = bump(3)\nend\n";
+        let (prog, start, errors) = load(src);
+        assert!(errors.is_empty());
+        let mut rt = Runtime::default();
+        eval_block(&prog.globals, &prog, &mut rt, 0).unwrap();
+        rt.globals = rt.decls.keys().cloned().collect();
+        eval_block(&start, &prog, &mut rt, 0).unwrap();
+        assert_eq!(eval("r", &mut rt), Ok("5".to_string()));
+        assert_eq!(eval("count", &mut rt), Ok("5".to_string()));
+        assert_eq!(eval("seen", &mut rt), Ok("[2, 3]".to_string()));
+        // 函数内同名的局部声明遮蔽了模块级变量
+        assert_eq!(eval("other", &mut rt), Ok("5".to_string()));
+    }
+
+    #[test]
+    fn check_indent_blocks() {
+        assert_eq!(indent_codes(&["if x:", "    say x", "say y"]), vec![None, None, None]);
+        assert_eq!(indent_codes(&["say x", "    say y"]), vec![None, Some("E0020")]);
+        assert_eq!(indent_codes(&["if x:", "say y"]), vec![None, Some("E0021")]);
+        assert_eq!(indent_codes(&["if x:", "        say x", "    say y"]), vec![None, None, Some("E0022")]);
+        assert_eq!(indent_codes(&["if x:", "    # 注释不影响缩进", "  # 任意缩进", "    say x"]), vec![None, None, None, None]);
+    }
+
+    #[test]
+    fn mixed_tabs_and_spaces() {
+        assert_eq!(check_codes("start:\n \tsay 1\nend\n"), vec!["E0017"]);
+        assert_eq!(check_codes("start:\n    say 1\n    if true:\n\t\tsay 2\nend\n"), vec!["E0018"]);
+        assert_eq!(check_codes("start:\n\tsay 1\n    say 2\nend\n"), vec!["E0019"]);
+        assert_eq!(expand_indent("\t  \tx", 4), "        x");
+        let args = |list: &[&str]| parse_args(&list.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(args(&["hitcode", "--tab-width", "8", "a.hc"]).map(|o| o.tab_width), Ok(Some(8)));
+        assert_eq!(args(&["hitcode", "check", "--tab-width=2", "a.hc"]).map(|o| (o.check_only, o.tab_width)), Ok((true, Some(2))));
+        assert!(args(&["hitcode", "--tab-width=0", "a.hc"]).is_err());
+    }
+
+    #[test]
+    fn fullwidth_punctuation_is_normalized() {
+        let (fixed, replaced) = normalize_fullwidth("if x > 5：");
+        assert_eq!(fixed, "if x > 5:");
+        assert_eq!(replaced.iter().map(|r| r.0).collect::<Vec<_>>(), vec!['：']);
+        // 字符串内部的全角符号保持不变
+        assert_eq!(normalize_fullwidth("say “你好，世界”（1）").0, r#"say "你好，世界"(1)"#);
+        assert_eq!(normalize_fullwidth(r#"var int x ＝ f（1，2）"#).0, "var int x = f(1,2)");
+        // 全角符号只给出警告，程序照常加载
+        assert_eq!(check_codes("start：\n    var int x ＝ 1\nend\n"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn fix_rewrites_fullwidth_punctuation() {
+        let path = env::temp_dir().join(format!("hitcode-fix-{}.hc", process::id()));
+        std::fs::write(&path, "# 注释：保持不变\nstart：\n    say “a，b”\nend\n").unwrap();
+        assert_eq!(fix_file(path.to_str().unwrap(), None).map_err(|e| e.message), Ok(2));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# 注释：保持不变\nstart:\n    say \"a，b\"\nend\n");
+        // 改写为中文关键字时在开头加上关键字指示
+        assert_eq!(fix_file(path.to_str().unwrap(), Some(Keywords::Zh)).map_err(|e| e.message), Ok(4));
+        let fixed = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(fixed, "# keywords: zh\n# 注释：保持不变\n开始:\n    说 \"a，b\"\n结束\n");
+    }
+
+    #[test]
+    fn chinese_keywords_by_pragma() {
+        assert_eq!(keyword_pragma("# keywords: zh"), Some(Ok(Keywords::Zh)));
+        assert_eq!(keyword_pragma("# 关键字：中文"), Some(Ok(Keywords::Zh)));
+        assert!(matches!(keyword_pragma("# keywords: fr"), Some(Err(_))));
+        assert_eq!(keyword_pragma("# 普通注释"), None);
+        assert_eq!(translate_keywords("变量 整数 x = 1", Keywords::En), "var int x = 1");
+        // 字符串中的内容和包含关键字的名字都不替换
+        assert_eq!(translate_keywords(r#"说 "如果" + 说明"#, Keywords::En), r#"say "如果" + 说明"#);
+        assert_eq!(translate_keywords("if x > 0:", Keywords::Zh), "如果 x > 0:");
+        let src = "# keywords: zh\n函数 问候(字符串 名字)->返回::字符串:\n    变量 字符串 y = 名字\n    结束(\"ok\")\n\n\
+                   开始:\n    变量 整数 x = 1\n    如果 x > 0:\n        x = 2\n    变量 r = 调用 问候(\"小明\")\n结束()\n";
+        let (prog, start, errors) = load(src);
+        assert!(errors.is_empty());
+        let mut rt = Runtime::default();
+        eval_block(&start, &prog, &mut rt, 0).unwrap();
+        assert_eq!(rt.variables["x"], "2");
+        assert_eq!(rt.variables["r"], r#""ok""#);
+        // 使用中文关键字的文件，诊断中也显示中文关键字
+        let source = SourceLine { text: r#"    var int x = "a""#.to_string(), number: 1 };
+        let diag = Diagnostic::new(Severity::Error, "E0100", "var 类型不匹配".to_string(), "demo.hc", &source, Some(r#""a""#), Keywords::Zh);
+        assert_eq!((diag.message.as_str(), diag.snippet.as_str(), diag.column), ("变量 类型不匹配", r#"    变量 整数 x = "a""#, 15));
+        // 没有指示的文件不能使用中文关键字
+        assert_eq!(check_codes("开始:\n    变量 整数 x = 1\n结束()\n"), vec!["E0015", "E0015", "E0015", "E0016"]);
+    }
+
+    #[test]
+    fn tr_fills_placeholders_in_both_locales() {
+        assert_eq!(tr_in(Lang::ZhCn, "E0107", &[&"f", &2, &1]), "函数 f 需要 2 个参数，但给了 1 个");
+        assert_eq!(tr_in(Lang::EnUs, "E0107", &[&"f", &2, &1]), "Function f takes 2 arguments but 1 were given");
+        // {0} {1} 按位置取参数，英文和中文的顺序可以不同
+        assert_eq!(tr_in(Lang::ZhCn, "W0001", &[&"switch x:", &1]), "switch x: 中的 case 标签 1 重复，后面的分支永远不会执行");
+        assert_eq!(tr_in(Lang::EnUs, "W0001", &[&"switch x:", &1]), "Duplicate case label 1 in switch x:; the later branch can never run");
+        // 缺少的参数保留占位符，目录中没有的键原样返回
+        assert_eq!(tr_in(Lang::EnUs, "E0203", &[]), "Function {} is not defined");
+        assert_eq!(tr_in(Lang::ZhCn, "no.such.key", &[]), "no.such.key");
+        assert_eq!(tr_in(Lang::ZhCn, "ctx.say", &[]), "say 执行失败:");
+        assert_eq!(tr_in(Lang::ZhCn, "ctx.for", &[]), "for 执行失败:");
+        assert_eq!(tr_in(Lang::ZhCn, "ctx.switch", &[]), "switch 执行失败:");
+        // --lang 在其他参数之前找出，写在出错的参数后面也生效
+        let args = |list: &[&str]| lang_arg(&list.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(args(&["hitcode", "--bogus", "--lang", "en-US", "a.hc"]), Some(Lang::EnUs));
+        assert_eq!(args(&["hitcode", "--lang=zh_CN.UTF-8", "--bogus"]), Some(Lang::ZhCn));
+        assert_eq!(args(&["hitcode", "a.hc", "--lang"]), None);
+    }
+
+    // 只有运行时才能发现的错误，hitcode check 不报告
+    const RUNTIME_ONLY: &[&str] = &[
+        "E0007", "E0103", "E0105", "E0109", "E0110", "E0111", "E0114", "E0115", "E0116", "E0117", "E0118",
+        "E0120", "E0122", "E0123", "E0124", "E0128", "E0131", "E0132", "E0133", "E0137", "E0204", "E0205",
+        "E0301", "E0302", "E0303", "E0401", "E0403", "E0501", "E0502", "E0503", "E0601", "E0800",
+    ];
+
+    #[test]
+    fn check_program_explanation_examples() {
+        // 每个错误码说明中的正确写法都能通过检查，错误写法（运行时错误除外）报告这个错误码
+        let examples = EXPLANATIONS.iter().filter(|e| !e.bad.is_empty() && !e.bad.starts_with('$'));
+        for e in examples {
+            assert_eq!(check_codes(e.good), Vec::<&str>::new(), "{} 的正确写法", e.code);
+            if e.code.starts_with('E') && !RUNTIME_ONLY.contains(&e.code) {
+                assert_eq!(check_codes(e.bad).first(), Some(&e.code), "{} 的错误写法", e.code);
+            }
+        }
+    }
+
+    #[test]
+    fn every_code_has_an_explanation() {
+        for (key, _, _) in MESSAGES.iter().filter(|(k, _, _)| k.len() == 5 && k.starts_with(['E', 'W'])) {
+            assert!(EXPLANATIONS.iter().any(|e| e.code == *key), "{} 没有说明", key);
+        }
+        // 不再使用的错误码只保留说明，消息目录中没有它
+        for e in EXPLANATIONS.iter().filter(|e| e.bad.is_empty()) {
+            assert!(!MESSAGES.iter().any(|(k, _, _)| *k == e.code), "{} 已经不再使用", e.code);
+        }
+    }
+
+    #[test]
+    fn lex_line_escapes() {
+        assert_eq!(lex_line(r#"say "a\tb\"c\\""#), Ok(r#"say "a\tb\"c\\""#.to_string()));
+        assert_eq!(lex_line(r#"say "\u{4E2D}文""#), Ok(r#"say "中文""#.to_string()));
+        assert_eq!(lex_line(r#"say "\q""#), Err(("E0026", r"\q".to_string())));
+        assert_eq!(lex_line(r#"say "\u{zz}""#), Err(("E0027", r"\u{zz}".to_string())));
+        assert_eq!(lex_line(r#"say "abc"#), Err(("E0024", r#""abc"#.to_string())));
+    }
+
+    #[test]
+    fn lex_line_raw_and_triple_strings() {
+        assert_eq!(lex_line(r#"say r"C:\new\t""#), Ok(r#"say "C:\\new\\t""#.to_string()));
+        // 原始字符串中的 \" 原样保留，不结束字符串
+        assert_eq!(lex_line(r#"say r"a\"b""#), Ok(r#"say "a\\\"b""#.to_string()));
+        assert_eq!(lex_line(r#"say """a "quoted" word""""#), Ok(r#"say "a \"quoted\" word""#.to_string()));
+        assert_eq!(lex_line(r#"say """abc"#), Err(("E0025", r#"""""#.to_string())));
+        // 单词中的 r 不是原始字符串的前缀
+        assert_eq!(lex_line(r#"say bar"\n""#), Ok(r#"say bar"\n""#.to_string()));
+    }
+
+    #[test]
+    fn triple_quoted_strings_span_lines() {
+        let (prog, start, errors) = load("start:\n    var str s = \"\"\"第一行\n  第二行 \"引号\" 完\"\"\"\n    var int n = 1\nend\n");
+        assert!(errors.is_empty());
+        let mut rt = Runtime::default();
+        eval_block(&start, &prog, &mut rt, 0).unwrap();
+        assert_eq!(display_value(&rt.variables["s"]), "第一行\n  第二行 \"引号\" 完");
+        assert_eq!(rt.variables["n"], "1");
+        assert_eq!(check_codes("start:\n    var str s = \"\"\"open\n    var int n = 1\nend\n"), vec!["E0025", "E0013"]);
+    }
+
+    #[test]
+    fn lex_line_comments() {
+        assert_eq!(lex_line(r##"say "# not a comment" # comment"##), Ok(r##"say "# not a comment" "##.to_string()));
+        assert_eq!(lex_line(r#"say "a // b" // comment"#), Ok(r#"say "a // b" "#.to_string()));
+        assert_eq!(lex_line(r#"say "/* kept */" + "x""#), Ok(r#"say "/* kept */" + "x""#.to_string()));
+        assert_eq!(lex_line("x = 1 /* one */ + 2"), Ok("x = 1   + 2".to_string()));
+        assert_eq!(lex_line("x = 1 /* open"), Err(("E0028", "/*".to_string())));
+    }
+
+    #[test]
+    fn doc_comments_attach_to_functions() {
+        let src = "## 向 who 打招呼。\n## 返回问候语。\n\nfunction greet(str who)->return::str:\n    var str msg = \"你好\" // 行尾注释\n    end(msg)\n\n\
+                   ## 没有函数跟在后面\n/*\n  跨行的块注释\n*/\nstart:\n    var str s = call greet(\"a\") # 注释\nend\n";
+        let (prog, start, errors) = load(src);
+        assert!(errors.is_empty());
+        assert_eq!(prog.function_docs["greet"], "向 who 打招呼。\n返回问候语。");
+        assert_eq!(prog.function_docs.len(), 1);
+        let mut rt = Runtime::default();
+        eval_block(&start, &prog, &mut rt, 0).unwrap();
+        assert_eq!(rt.variables["s"], r#""你好""#);
+    }
+
+    #[test]
+    fn chained_method_calls() {
+        let mut rt = run("var str name = \"  Ada Lovelace \"\nlist int xs = [3, 1, 2]\n");
+        assert_eq!(eval("name.trim().upper()", &mut rt), Ok(r#""ADA LOVELACE""#.to_string()));
+        assert_eq!(eval(r#"name.trim().split(" ").len()"#, &mut rt), Ok("2".to_string()));
+        assert_eq!(eval(r#""a-b".replace("-", "+")"#, &mut rt), Ok(r#""a+b""#.to_string()));
+        assert_eq!(eval(r#"xs.sorted().reversed().join(", ")"#, &mut rt), Ok(r#""3, 2, 1""#.to_string()));
+        assert_eq!(eval("xs.index_of(2)", &mut rt), Ok("2".to_string()));
+        assert_eq!(eval("(2.5).floor() + (-3).abs()", &mut rt), Ok("5".to_string()));
+        assert_eq!(eval("xs[0].pow(2).to_str()", &mut rt), Ok(r#""9""#.to_string()));
+        assert!(eval(r#""12a".to_int()"#, &mut rt).is_err());
+        assert!(eval("xs.upper()", &mut rt).is_err());
+        assert_eq!(check_codes("start:\n    var int n = \"abc\".upper()\nend\n"), vec!["E0101"]);
+    }
+
+    #[test]
+    fn split_call_pairs_parens() {
+        assert_eq!(split_call("f(a, g(b))"), ("f", "a, g(b)"));
+        assert_eq!(split_call(r#"f(")", 1)"#), ("f", r#"")", 1"#));
+        assert_eq!(split_call("name().upper()"), ("name", ""));
+        assert_eq!(split_call("x"), ("x", ""));
+        // 括号不配对时不会越界
+        assert_eq!(split_call("a)b("), ("a)b", ""));
+        assert_eq!(split_call("f(a"), ("f", "a"));
+        assert_eq!(call_name("f(1)"), Some("f"));
+        assert_eq!(call_name(r#"f("(")"#), Some("f"));
+        assert_eq!(call_name("f(1)(2)"), None);
+        assert_eq!(call_name("name().upper()"), None);
+        assert_eq!(call_name("a.b(1)"), None);
+        assert_eq!(call_name("a)b("), None);
+        assert_eq!(call_name("f(a"), None);
+    }
+
+    #[test]
+    fn split_method_call_finds_last_call() {
+        assert_eq!(split_method_call("xs.get(0)"), Some(("xs", "get", "0")));
+        assert_eq!(split_method_call("name().upper()"), Some(("name()", "upper", "")));
+        assert_eq!(split_method_call(r#"s.split(")")"#), Some(("s", "split", r#"")""#)));
+        assert_eq!(split_method_call("f(x)"), None);
+        assert_eq!(split_method_call("a.b(c))"), None);
+        assert_eq!(split_method_call("a)b("), None);
+    }
+
+    #[test]
+    fn calls_are_expressions() {
+        let mut rt = run("var t = divmod(7, 2)\nvar int n = divmod(9, 4)[0] + 1\nvar int m = call divmod(5, 3)[1]\n\
+                     var str s = call where(1)[0].upper()\n");
+        assert_eq!(eval("t", &mut rt), Ok("(3, 1)".to_string()));
+        assert_eq!(eval("n", &mut rt), Ok("3".to_string()));
+        assert_eq!(eval("divmod(t[0], 2)", &mut rt), Ok("(1, 1)".to_string()));
+        // call f(x) 后面的下标和方法调用作用在返回值上
+        assert_eq!(eval("m", &mut rt), Ok("2".to_string()));
+        assert!(eval("s", &mut rt).unwrap().starts_with(r#""WHERE(N: 1)"#));
+        assert_eq!(check_codes("start:\n    var int x = nothing(1)\nend\n"), vec!["E0203"]);
+        // 作为语句调用未定义的函数同样报告 E0203
+        assert_eq!(check_codes("start:\n    nothing(1)\n    call nothing(2)\nend\n"), vec!["E0203", "E0203"]);
+        assert_eq!(exec("nothing(1)", &mut rt).map_err(|e| e.code), Err("E0203"));
+    }
+}